@@ -0,0 +1,99 @@
+#![allow(non_snake_case)]
+
+//-----------------------------------------------------------------------------
+// Streaming conversion benchmark
+//-----------------------------------------------------------------------------
+// Writes a synthetic legacy Items.xml with 100000 records (or the number given) to the temp
+// folder, converts it with the generic rule converter and prints the time taken and the peak
+// memory use of the process, which stays far below the size of the files.
+//
+//   cargo run --release --example stream_benchmark [records]
+
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+use JA2_ConvertXMLData::rules::RuleSet;
+
+// Items with numbered attachment tags, so every record has rules to apply
+const RULES: &str = r#"
+[[table]]
+file = "Items.xml"
+root = "ITEMLIST"
+record = "ITEM"
+
+[[table.rule]]
+source = "DefaultAttachment{n}"
+target = 'DefaultAttachment id = "{n}" item = "{value}"'
+skip = "0"
+"#;
+
+fn writeItems(filepath: &Path, records: usize)
+{
+    let mut writer = BufWriter::new(File::create(filepath).unwrap());
+    writeln!(writer, "<ITEMLIST>").unwrap();
+    for i in 0..records
+    {
+        writeln!(writer, "\t<ITEM>").unwrap();
+        writeln!(writer, "\t\t<uiIndex>{}</uiIndex>", i).unwrap();
+        writeln!(writer, "\t\t<szItemName>Item {}</szItemName>", i).unwrap();
+        writeln!(writer, "\t\t<szLongItemName>Synthetic item number {} &amp; friends</szLongItemName>", i).unwrap();
+        writeln!(writer, "\t\t<szItemDesc>Made up for the streaming benchmark, nothing to see here.</szItemDesc>").unwrap();
+        writeln!(writer, "\t\t<usItemClass>{}</usItemClass>", 1 << (i % 16)).unwrap();
+        writeln!(writer, "\t\t<ubWeight>{}</ubWeight>", i % 250).unwrap();
+        writeln!(writer, "\t\t<usPrice>{}</usPrice>", i * 7 % 5000).unwrap();
+        for n in 0..8
+        {
+            let item = if n < i % 4 { (i + n) % 1500 } else { 0 };
+            writeln!(writer, "\t\t<DefaultAttachment{}>{}</DefaultAttachment{}>", n, item, n).unwrap();
+        }
+        writeln!(writer, "\t</ITEM>").unwrap();
+    }
+    writeln!(writer, "</ITEMLIST>").unwrap();
+    writer.flush().unwrap();
+}
+
+/// Peak resident memory of the process in kB, where the system reports it
+fn peakMemory() -> Option<u64>
+{
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn megabytes(filepath: &Path) -> f64
+{
+    std::fs::metadata(filepath).map(|m| m.len()).unwrap_or(0) as f64 / 1_048_576.0
+}
+
+fn main()
+{
+    let records = env::args().nth(1).and_then(|r| r.parse().ok()).unwrap_or(100_000);
+    let rules: RuleSet = toml::from_str(RULES).unwrap();
+    let table = rules.table("Items.xml").unwrap();
+
+    let dir = env::temp_dir().join(format!("JA2-ConvertXMLData-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("Items.xml");
+    let target = dir.join("Items out.xml");
+
+    let start = Instant::now();
+    writeItems(&source, records);
+    println!("Wrote {} records, {:.1} MB in {:.2?}", records, megabytes(&source), start.elapsed());
+    let before = peakMemory();
+
+    let start = Instant::now();
+    table.convert(&source, &target).unwrap();
+    let elapsed = start.elapsed();
+
+    println!("Converted to {:.1} MB in {:.2?}, {:.0} records/s, {:.1} MB/s", megabytes(&target), elapsed,
+        records as f64 / elapsed.as_secs_f64(), megabytes(&source) / elapsed.as_secs_f64());
+    match (before, peakMemory())
+    {
+        (Some(before), Some(after)) => println!("Peak memory {} kB, {} kB before converting", after, before),
+        _ => println!("Peak memory is not reported on this system")
+    }
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,173 @@
+use std::fmt::Write;
+use serde::Serialize;
+
+use crate::mercopinions::MercOpinions;
+
+
+// Number of mercs listed as most loved and most hated
+pub const STANDINGS: usize = 10;
+
+
+#[derive(Serialize)]
+pub struct Merc
+{
+    pub uiIndex: u8,
+    pub zNickname: String
+}
+
+/// `merc` likes `target`, but `target` dislikes `merc`
+#[derive(Serialize)]
+pub struct Asymmetry
+{
+    pub merc: Merc,
+    pub target: Merc,
+    pub modifier: i32,
+    pub reverse: i32
+}
+
+#[derive(Serialize)]
+pub struct SelfOpinion
+{
+    pub merc: Merc,
+    pub modifier: i32
+}
+
+/// Sum of the opinions other mercs have about `merc`
+#[derive(Serialize)]
+pub struct Standing
+{
+    pub merc: Merc,
+    pub total: i32,
+    pub opinions: u32
+}
+
+#[derive(Serialize)]
+pub struct Bucket
+{
+    pub modifier: i32,
+    pub count: u32
+}
+
+
+//-----------------------------------------------------------------------------
+// Report
+//-----------------------------------------------------------------------------
+// Zero opinions are no opinion, self-opinions don't count for a merc's standing.
+
+#[derive(Serialize)]
+pub struct Report
+{
+    pub asymmetric: Vec<Asymmetry>,
+    pub ignored: Vec<Merc>,
+    pub selfOpinions: Vec<SelfOpinion>,
+    pub mostLoved: Vec<Standing>,
+    pub mostHated: Vec<Standing>,
+    pub histogram: Vec<Bucket>
+}
+impl Report
+{
+    pub fn analyze(mercOpinions: &MercOpinions) -> Report
+    {
+        let merc = |uiIndex: u8| Merc{uiIndex, zNickname: mercOpinions.name(uiIndex)};
+        let opinion = |from: u8, to: usize| mercOpinions.position(from)
+            .and_then(|k| mercOpinions.opinions[k].get(to).copied())
+            .unwrap_or(0);
+
+        let mut asymmetric = Vec::new();
+        let mut selfOpinions = Vec::new();
+        let mut received: Vec<(u8, i32, u32)> = mercOpinions.index.iter().map(|i| (*i, 0, 0)).collect();
+        let mut histogram: Vec<Bucket> = Vec::new();
+
+        for (k, from) in mercOpinions.index.iter().enumerate()
+        {
+            for (j, modifier) in mercOpinions.opinions[k].iter().enumerate()
+            {
+                if *modifier == 0 { continue; }
+
+                match histogram.iter_mut().find(|b| b.modifier == *modifier)
+                {
+                    Some(bucket) => bucket.count += 1,
+                    None => histogram.push(Bucket{modifier: *modifier, count: 1})
+                }
+
+                if j == *from as usize
+                {
+                    selfOpinions.push(SelfOpinion{merc: merc(*from), modifier: *modifier});
+                    continue;
+                }
+
+                if let Some(standing) = received.iter_mut().find(|(i, _, _)| *i as usize == j)
+                {
+                    standing.1 += modifier;
+                    standing.2 += 1;
+                }
+
+                let reverse = opinion(j as u8, *from as usize);
+                if *modifier > 0 && reverse < 0
+                {
+                    asymmetric.push(Asymmetry{merc: merc(*from), target: merc(j as u8), modifier: *modifier, reverse});
+                }
+            }
+        }
+
+        let ignored = received.iter().filter(|(_, _, count)| *count == 0).map(|(i, _, _)| merc(*i)).collect();
+
+        received.sort_by_key(|(_, total, _)| -total);
+        let mostLoved = received.iter().filter(|(_, total, _)| *total > 0).take(STANDINGS)
+            .map(|(i, total, opinions)| Standing{merc: merc(*i), total: *total, opinions: *opinions})
+            .collect();
+        let mostHated = received.iter().rev().filter(|(_, total, _)| *total < 0).take(STANDINGS)
+            .map(|(i, total, opinions)| Standing{merc: merc(*i), total: *total, opinions: *opinions})
+            .collect();
+
+        histogram.sort_by_key(|b| b.modifier);
+
+        Report{asymmetric, ignored, selfOpinions, mostLoved, mostHated, histogram}
+    }
+
+    pub fn toText(&self) -> String
+    {
+        let mut text = String::new();
+
+        writeln!(text, "Asymmetric relationships: {}", self.asymmetric.len()).unwrap();
+        for a in &self.asymmetric
+        {
+            writeln!(text, "\t{} likes {} ({:+}), {} dislikes {} ({:+})",
+                a.merc.zNickname, a.target.zNickname, a.modifier, a.target.zNickname, a.merc.zNickname, a.reverse).unwrap();
+        }
+
+        writeln!(text, "\nMercs no one has an opinion about: {}", self.ignored.len()).unwrap();
+        for merc in &self.ignored
+        {
+            writeln!(text, "\t{} ({})", merc.zNickname, merc.uiIndex).unwrap();
+        }
+
+        writeln!(text, "\nSelf-opinions: {}", self.selfOpinions.len()).unwrap();
+        for s in &self.selfOpinions
+        {
+            writeln!(text, "\t{} ({}): {:+}", s.merc.zNickname, s.merc.uiIndex, s.modifier).unwrap();
+        }
+
+        writeln!(text, "\nMost loved:").unwrap();
+        for s in &self.mostLoved
+        {
+            writeln!(text, "\t{:+5}  {} ({} opinions)", s.total, s.merc.zNickname, s.opinions).unwrap();
+        }
+
+        writeln!(text, "\nMost hated:").unwrap();
+        for s in &self.mostHated
+        {
+            writeln!(text, "\t{:+5}  {} ({} opinions)", s.total, s.merc.zNickname, s.opinions).unwrap();
+        }
+
+        writeln!(text, "\nModifiers:").unwrap();
+        let largest = self.histogram.iter().map(|b| b.count).max().unwrap_or(1);
+        for b in &self.histogram
+        {
+            let bar = "#".repeat(((b.count * 40).div_ceil(largest)) as usize);
+            writeln!(text, "\t{:+4} {:6}  {}", b.modifier, b.count, bar).unwrap();
+        }
+
+        text
+    }
+}
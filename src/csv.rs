@@ -0,0 +1,362 @@
+use std::path::Path;
+use std::collections::HashMap;
+use std::io::Write;
+use quick_xml::events::Event;
+use quick_xml::escape::escape;
+use quick_xml::Reader;
+
+use crate::rules::TableRule;
+use crate::createOutput;
+use crate::mercopinions::{MercOpinions, checkOpinionId, setOpinion};
+
+
+//-----------------------------------------------------------------------------
+// MercOpinions as a matrix
+//-----------------------------------------------------------------------------
+// One row per merc with uiIndex and zNickname, then one column per target merc. Column headers
+// are the target nicknames, the target id is the column position.
+
+pub fn opinionsToRows(mercOpinions: &MercOpinions) -> Vec<Vec<String>>
+{
+    let columns = mercOpinions.opinions.iter().map(|o| o.len()).max().unwrap_or(0).max(255);
+
+    let mut header = vec!["uiIndex".to_string(), "zNickname".to_string()];
+    for j in 0..columns
+    {
+        let nickname = u8::try_from(j).ok()
+            .and_then(|j| mercOpinions.position(j))
+            .map(|k| mercOpinions.nicknames[k].clone());
+        header.push(nickname.unwrap_or_else(|| j.to_string()));
+    }
+
+    let mut rows = vec![header];
+    for k in 0..mercOpinions.index.len()
+    {
+        let mut row = vec![mercOpinions.index[k].to_string(), mercOpinions.nicknames[k].clone()];
+        for j in 0..columns
+        {
+            row.push(mercOpinions.opinions[k].get(j).copied().unwrap_or(0).to_string());
+        }
+        rows.push(row);
+    }
+    rows
+}
+
+pub fn opinionsFromRows(rows: &[Vec<String>]) -> Result<MercOpinions, String>
+{
+    match rows.first()
+    {
+        Some(header) if header.len() >= 2 && header[0] == "uiIndex" && header[1] == "zNickname" => {}
+        _ => return Err("First row must start with uiIndex,zNickname".to_string())
+    }
+
+    let mut mercOpinions = MercOpinions::new();
+    for (line, row) in rows.iter().enumerate().skip(1)
+    {
+        let index = row[0].trim().parse::<u8>()
+            .map_err(|_| format!("Row {}: invalid uiIndex '{}'", line + 1, row[0]))?;
+        let nickname = row.get(1).cloned().unwrap_or_default();
+
+        let mut opinions = Vec::new();
+        for (j, cell) in row.iter().enumerate().skip(2)
+        {
+            let value = match cell.trim()
+            {
+                "" => 0,
+                cell => cell.parse::<i32>()
+                    .map_err(|_| format!("Row {}, column {}: invalid modifier '{}'", line + 1, j + 1, cell))?
+            };
+            // Columns past the last merc id only may be empty
+            if value != 0
+            {
+                let n = checkOpinionId(j - 2).map_err(|e| format!("Row {}, column {}: {}", line + 1, j + 1, e))?;
+                setOpinion(&mut opinions, n, value);
+            }
+        }
+
+        mercOpinions.index.push(index);
+        mercOpinions.nicknames.push(nickname);
+        mercOpinions.opinions.push(opinions);
+    }
+    Ok(mercOpinions)
+}
+
+
+//-----------------------------------------------------------------------------
+// Other tables, one row per record
+//-----------------------------------------------------------------------------
+// Columns are the fields of the old format. Converted elements are turned back into their
+// numbered tags with the table's rules, so old and new format files give the same columns.
+// Fields inside nested elements get the path to them as column, eg. "Attachments/Attachment",
+// and repeated fields a number from the second one on, eg. "Attachments/Attachment#2".
+
+pub fn tableToRows(filepath: &Path, table: &TableRule) -> Result<Vec<Vec<String>>, String>
+{
+    let mut columns: Vec<String> = Vec::new();
+    let mut records: Vec<HashMap<String, String>> = Vec::new();
+
+    let mut reader = Reader::from_file(filepath)
+        .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut inRecord = false;
+    // Open elements below the record with whether they have child elements
+    let mut path: Vec<(String, bool)> = Vec::new();
+    // Number of times each field was seen in the current record, by path
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut text = String::new();
+    loop
+    {
+        let field = match reader.read_event_into(&mut buf)
+        {
+            Err(e) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e)),
+            Ok(Event::Eof) => break,
+
+            Ok(Event::Start(ref element)) =>
+            {
+                let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                if inRecord
+                {
+                    if let Some((_, parent)) = path.last_mut() { *parent = true; }
+                    path.push((occurrence(&mut seen, &path, &name), false));
+                }
+                else if name == table.record
+                {
+                    inRecord = true;
+                    seen.clear();
+                    records.push(HashMap::new());
+                }
+                text.clear();
+                None
+            }
+            Ok(Event::Text(ref e)) if inRecord =>
+            {
+                let value = e.unescape()
+                    .map_err(|e| format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e))?;
+                text.push_str(&value);
+                None
+            }
+            Ok(Event::CData(ref e)) if inRecord =>
+            {
+                text.push_str(&String::from_utf8_lossy(e));
+                None
+            }
+            Ok(Event::Empty(ref element)) if inRecord =>
+            {
+                if let Some((_, parent)) = path.last_mut() { *parent = true; }
+                let converted = match path.is_empty()
+                {
+                    true => table.rules.iter().find_map(|r| r.matchTarget(element).map(|(n, v)| (r.sourceTag(n), v))),
+                    false => None
+                };
+                match converted
+                {
+                    Some(field) => Some(field),
+                    None =>
+                    {
+                        let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                        let name = occurrence(&mut seen, &path, &name);
+                        Some((column(&path, &name), String::new()))
+                    }
+                }
+            }
+            Ok(Event::End(_)) if inRecord =>
+            {
+                match path.pop()
+                {
+                    None => { inRecord = false; None }
+                    // Containers only hold their children
+                    Some((_, true)) => None,
+                    Some((name, false)) => Some((column(&path, &name), std::mem::take(&mut text)))
+                }
+            }
+            _ => None
+        };
+
+        if let Some((name, value)) = field
+        {
+            if !records.last().unwrap().contains_key(&name) && !columns.contains(&name) { columns.push(name.clone()); }
+            records.last_mut().unwrap().insert(name, value);
+        }
+        buf.clear();
+    }
+
+    let mut rows = vec![columns.clone()];
+    for mut record in records
+    {
+        let row = columns.iter().map(|c| record.remove(c).unwrap_or_default()).collect();
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+// Name of the field with its number if it's there more than once under the same parent
+fn occurrence(seen: &mut HashMap<String, usize>, path: &[(String, bool)], name: &str) -> String
+{
+    let count = seen.entry(column(path, name)).or_insert(0);
+    *count += 1;
+    match *count
+    {
+        1 => name.to_string(),
+        n => format!("{}#{}", name, n)
+    }
+}
+
+fn column(path: &[(String, bool)], name: &str) -> String
+{
+    path.iter().map(|(p, _)| p.as_str()).chain([name]).collect::<Vec<_>>().join("/")
+}
+
+// Tag of a column path segment, without its number
+fn tag(segment: &str) -> &str
+{
+    segment.split_once('#').map_or(segment, |(tag, _)| tag)
+}
+
+/// Rows back to an old format xml file, which can then go through the table's converter.
+/// Empty cells of plain fields are written as empty fields. Empty numbered tags, nested and
+/// repeated fields are left out, those are cells of records that don't have the field.
+pub fn rowsToXml(rows: &[Vec<String>], table: &TableRule) -> Vec<u8>
+{
+    let mut buffer = Vec::new();
+    writeln!(buffer, "<{}>", table.root).unwrap();
+
+    if let Some((header, records)) = rows.split_first()
+    {
+        for record in records
+        {
+            writeln!(buffer, "\t<{}>", table.record).unwrap();
+            // Nested elements that are open
+            let mut open: Vec<&str> = Vec::new();
+            for (column, value) in header.iter().zip(record.iter())
+            {
+                let segments: Vec<&str> = column.split('/').collect();
+                let (name, parents) = segments.split_last().unwrap();
+                if value.is_empty() && (!parents.is_empty() || name.contains('#') || table.rule(name).is_some()) { continue; }
+
+                let common = open.iter().zip(parents.iter()).take_while(|(a, b)| a == b).count();
+                while open.len() > common
+                {
+                    let segment = open.pop().unwrap();
+                    writeln!(buffer, "\t\t{}</{}>", "\t".repeat(open.len()), tag(segment)).unwrap();
+                }
+                for segment in &parents[common..]
+                {
+                    writeln!(buffer, "\t\t{}<{}>", "\t".repeat(open.len()), tag(segment)).unwrap();
+                    open.push(segment);
+                }
+
+                if open.is_empty()
+                {
+                    let tag = tag(name);
+                    write_tag_s!(buffer, value, tag, true).unwrap();
+                }
+                else
+                {
+                    writeln!(buffer, "\t\t{}<{}>{}</{}>", "\t".repeat(open.len()), tag(name), escape(value.as_str()), tag(name)).unwrap();
+                }
+            }
+            while let Some(segment) = open.pop()
+            {
+                writeln!(buffer, "\t\t{}</{}>", "\t".repeat(open.len()), tag(segment)).unwrap();
+            }
+            writeln!(buffer, "\t</{}>", table.record).unwrap();
+        }
+    }
+
+    writeln!(buffer, "</{}>", table.root).unwrap();
+    buffer
+}
+
+
+//-----------------------------------------------------------------------------
+// Reading and writing
+//-----------------------------------------------------------------------------
+pub fn saveCsv(rows: &[Vec<String>], filepath: &Path) -> Result<(), String>
+{
+    let mut buffer = Vec::new();
+    for row in rows
+    {
+        let cells: Vec<String> = row.iter().map(|c| quote(c)).collect();
+        writeln!(buffer, "{}", cells.join(",")).unwrap();
+    }
+
+    let mut file = createOutput(filepath)?;
+    file.write_all(&buffer)
+        .and_then(|_| file.flush())
+        .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+}
+
+pub fn loadCsv(filepath: &Path) -> Result<Vec<Vec<String>>, String>
+{
+    let text = std::fs::read_to_string(filepath)
+        .map_err(|e| format!("Could not read file {}: {}", filepath.display(), e))?;
+    parseCsv(&text).map_err(|e| format!("{}: {}", filepath.display(), e))
+}
+
+/// Comma separated with "quoted" cells, as spreadsheets write it. Semicolons are accepted as
+/// separator too, for spreadsheets in locales that use them.
+pub fn parseCsv(text: &str) -> Result<Vec<Vec<String>>, String>
+{
+    let text = text.trim_start_matches('\u{feff}');
+    let separator = match text.lines().next()
+    {
+        Some(header) if !header.contains(',') && header.contains(';') => ';',
+        _ => ','
+    };
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next()
+    {
+        if quoted
+        {
+            match c
+            {
+                '"' if chars.peek() == Some(&'"') => { cell.push('"'); chars.next(); }
+                '"' => quoted = false,
+                _ => cell.push(c)
+            }
+            continue;
+        }
+
+        match c
+        {
+            '"' => quoted = true,
+            '\r' => {}
+            '\n' =>
+            {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            c if c == separator => row.push(std::mem::take(&mut cell)),
+            _ => cell.push(c)
+        }
+    }
+    if quoted { return Err("Unterminated quoted cell".to_string()); }
+    if !cell.is_empty() || !row.is_empty()
+    {
+        row.push(cell);
+        rows.push(row);
+    }
+
+    // Spreadsheets like to add empty lines at the end
+    rows.retain(|r| r.iter().any(|c| !c.is_empty()));
+    Ok(rows)
+}
+
+fn quote(cell: &str) -> String
+{
+    if cell.contains([',', ';', '"', '\n', '\r']) || cell.starts_with(' ') || cell.ends_with(' ')
+    {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    }
+    else
+    {
+        cell.to_string()
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::Path;
+use std::collections::HashMap;
+use rusqlite::{params, Connection};
+
+use crate::mercopinions::{MercOpinions, checkOpinionId, setOpinion};
+
+
+//-----------------------------------------------------------------------------
+// SQLite database of a TableData folder
+//-----------------------------------------------------------------------------
+// Every xml file gets one table named after it (German.Items.xml -> German_Items), listed in
+// xml_tables with its file name so it can be written back. MercOpinions is stored as
+//   MercOpinions(uiIndex, zNickname)
+//   merc_opinion(merc_id, target_id, modifier)    non-zero opinions only
+// Other tables have one text column per field of the old format, in the order of the csv export.
+
+pub struct Database
+{
+    pub connection: Connection
+}
+impl Database
+{
+    pub const FILE: &'static str = "TableData.sqlite";
+
+    /// Creates a new database, an existing file is replaced
+    pub fn create(filepath: &Path) -> Result<Database, String>
+    {
+        if filepath.exists()
+        {
+            std::fs::remove_file(filepath).map_err(|e| format!("Could not replace {}: {}", filepath.display(), e))?;
+        }
+        std::fs::create_dir_all(filepath.parent().unwrap()).unwrap();
+
+        let database = Database::open(filepath)?;
+        database.connection.execute_batch(
+            "CREATE TABLE xml_tables (name TEXT PRIMARY KEY, file TEXT NOT NULL);"
+        ).map_err(sqlError)?;
+        Ok(database)
+    }
+
+    pub fn open(filepath: &Path) -> Result<Database, String>
+    {
+        let connection = Connection::open(filepath)
+            .map_err(|e| format!("Could not open database {}: {}", filepath.display(), e))?;
+        Ok(Database{connection})
+    }
+
+    /// Table names and xml file names of all tables in the database
+    pub fn tables(&self) -> Result<Vec<(String, String)>, String>
+    {
+        let mut statement = self.connection.prepare("SELECT name, file FROM xml_tables ORDER BY rowid").map_err(sqlError)?;
+        let tables = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(sqlError)?;
+        tables.collect::<Result<Vec<_>, _>>().map_err(sqlError)
+    }
+
+    pub fn writeMercOpinions(&mut self, mercOpinions: &MercOpinions) -> Result<(), String>
+    {
+        let transaction = self.connection.transaction().map_err(sqlError)?;
+        transaction.execute_batch(
+            "CREATE TABLE MercOpinions (uiIndex INTEGER PRIMARY KEY, zNickname TEXT NOT NULL);
+             CREATE TABLE merc_opinion (merc_id INTEGER NOT NULL REFERENCES MercOpinions(uiIndex), target_id INTEGER NOT NULL,
+                                        modifier INTEGER NOT NULL, PRIMARY KEY (merc_id, target_id));"
+        ).map_err(sqlError)?;
+        transaction.execute("INSERT INTO xml_tables VALUES (?1, ?2)", params!["MercOpinions", MercOpinions::FILE]).map_err(sqlError)?;
+
+        // Records are numbered from 1 in file order in the messages
+        let mut records: HashMap<u8, usize> = HashMap::new();
+        for (k, i) in mercOpinions.index.iter().enumerate()
+        {
+            if let Some(first) = records.insert(*i, k)
+            {
+                return Err(format!("Record {} ({}) has uiIndex {}, which record {} ({}) has already, every merc can only be in the database once",
+                    k + 1, mercOpinions.nicknames[k], i, first + 1, mercOpinions.nicknames[first]));
+            }
+            transaction.execute("INSERT INTO MercOpinions VALUES (?1, ?2)", params![i, mercOpinions.nicknames[k]]).map_err(sqlError)?;
+            for (j, modifier) in mercOpinions.opinions[k].iter().enumerate()
+            {
+                if *modifier != 0
+                {
+                    transaction.execute("INSERT INTO merc_opinion VALUES (?1, ?2, ?3)", params![i, j, modifier]).map_err(sqlError)?;
+                }
+            }
+        }
+        transaction.commit().map_err(sqlError)
+    }
+
+    pub fn readMercOpinions(&self) -> Result<MercOpinions, String>
+    {
+        let mut mercOpinions = MercOpinions::new();
+
+        let mut statement = self.connection.prepare("SELECT uiIndex, zNickname FROM MercOpinions ORDER BY uiIndex").map_err(sqlError)?;
+        let records = statement.query_map([], |row| Ok((row.get::<_, u8>(0)?, row.get::<_, String>(1)?))).map_err(sqlError)?;
+        for record in records
+        {
+            let (index, nickname) = record.map_err(sqlError)?;
+            mercOpinions.index.push(index);
+            mercOpinions.nicknames.push(nickname);
+            mercOpinions.opinions.push(Vec::new());
+        }
+
+        let mut statement = self.connection.prepare("SELECT merc_id, target_id, modifier FROM merc_opinion").map_err(sqlError)?;
+        let opinions = statement.query_map([], |row| Ok((row.get::<_, u8>(0)?, row.get::<_, usize>(1)?, row.get::<_, i32>(2)?))).map_err(sqlError)?;
+        for opinion in opinions
+        {
+            let (merc, target, modifier) = opinion.map_err(sqlError)?;
+            match mercOpinions.position(merc)
+            {
+                Some(k) =>
+                {
+                    let target = checkOpinionId(target).map_err(|e| format!("merc_opinion of merc {}: {}", merc, e))?;
+                    setOpinion(&mut mercOpinions.opinions[k], target, modifier);
+                }
+                None => println!("Warning: merc_opinion references merc {} which is not in MercOpinions", merc)
+            }
+        }
+
+        Ok(mercOpinions)
+    }
+
+    /// Writes the rows of a table, the first row holds the column names
+    pub fn writeRows(&mut self, file: &str, rows: &[Vec<String>]) -> Result<(), String>
+    {
+        let name = tableName(file);
+        let header = match rows.first()
+        {
+            Some(header) => header,
+            None => return Ok(())
+        };
+
+        let columns: Vec<String> = header.iter().map(|c| format!("\"{}\" TEXT", c.replace('"', "\"\""))).collect();
+        let placeholders: Vec<String> = (1..=header.len()).map(|i| format!("?{}", i)).collect();
+
+        let transaction = self.connection.transaction().map_err(sqlError)?;
+        transaction.execute(&format!("CREATE TABLE \"{}\" ({})", name, columns.join(", ")), []).map_err(sqlError)?;
+        transaction.execute("INSERT INTO xml_tables VALUES (?1, ?2)", params![name, file]).map_err(sqlError)?;
+        {
+            let mut insert = transaction.prepare(&format!("INSERT INTO \"{}\" VALUES ({})", name, placeholders.join(", "))).map_err(sqlError)?;
+            for row in &rows[1..]
+            {
+                // Empty cells are fields the record doesn't have
+                let values = row.iter().map(|c| if c.is_empty() { None } else { Some(c.as_str()) });
+                insert.execute(rusqlite::params_from_iter(values)).map_err(sqlError)?;
+            }
+        }
+        transaction.commit().map_err(sqlError)
+    }
+
+    /// Reads the rows of a table in the layout of writeRows. Numbers written by SQL updates are accepted too.
+    pub fn readRows(&self, name: &str) -> Result<Vec<Vec<String>>, String>
+    {
+        let mut statement = self.connection.prepare(&format!("SELECT * FROM \"{}\" ORDER BY rowid", name)).map_err(sqlError)?;
+        let header: Vec<String> = statement.column_names().iter().map(|c| c.to_string()).collect();
+        let count = header.len();
+
+        let mut rows = vec![header];
+        let records = statement.query_map([], |row| {
+            (0..count).map(|i| {
+                let value = match row.get_ref(i)?
+                {
+                    rusqlite::types::ValueRef::Null => String::new(),
+                    rusqlite::types::ValueRef::Integer(v) => v.to_string(),
+                    rusqlite::types::ValueRef::Real(v) => v.to_string(),
+                    rusqlite::types::ValueRef::Text(v) | rusqlite::types::ValueRef::Blob(v) => String::from_utf8_lossy(v).into_owned()
+                };
+                Ok(value)
+            }).collect::<Result<Vec<String>, _>>()
+        }).map_err(sqlError)?;
+        for record in records
+        {
+            rows.push(record.map_err(sqlError)?);
+        }
+        Ok(rows)
+    }
+}
+
+
+/// Table name of an xml file, eg. German.Items.xml -> German_Items
+pub fn tableName(file: &str) -> String
+{
+    let stem = file.strip_suffix(".xml").unwrap_or(file);
+    stem.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+fn sqlError(e: rusqlite::Error) -> String
+{
+    format!("Database error: {}", e)
+}
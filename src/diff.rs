@@ -0,0 +1,164 @@
+use crate::mercopinions::MercOpinions;
+use crate::rules::TableRule;
+
+
+//-----------------------------------------------------------------------------
+// MercOpinions
+//-----------------------------------------------------------------------------
+// Records are matched by uiIndex, so reordered files have no changes. A missing opinion is 0.
+
+pub struct OpinionChange
+{
+    pub merc: u8,
+    pub target: usize,
+    pub old: i32,
+    pub new: i32
+}
+
+pub struct OpinionDiff
+{
+    pub added: Vec<u8>,
+    pub removed: Vec<u8>,
+    /// uiIndex, old and new nickname
+    pub nicknames: Vec<(u8, String, String)>,
+    /// Opinion changes of records in both files
+    pub opinions: Vec<OpinionChange>
+}
+impl OpinionDiff
+{
+    pub fn diffMercOpinions(old: &MercOpinions, new: &MercOpinions) -> OpinionDiff
+    {
+        let mut diff = OpinionDiff{added: Vec::new(), removed: Vec::new(), nicknames: Vec::new(), opinions: Vec::new()};
+
+        for (k, merc) in old.index.iter().enumerate()
+        {
+            match new.position(*merc)
+            {
+                None => diff.removed.push(*merc),
+                Some(n) =>
+                {
+                    if old.nicknames[k] != new.nicknames[n]
+                    {
+                        diff.nicknames.push((*merc, old.nicknames[k].clone(), new.nicknames[n].clone()));
+                    }
+                    let columns = old.opinions[k].len().max(new.opinions[n].len());
+                    for target in 0..columns
+                    {
+                        let before = old.opinions[k].get(target).copied().unwrap_or(0);
+                        let after = new.opinions[n].get(target).copied().unwrap_or(0);
+                        if before != after
+                        {
+                            diff.opinions.push(OpinionChange{merc: *merc, target, old: before, new: after});
+                        }
+                    }
+                }
+            }
+        }
+        diff.added = new.index.iter().filter(|i| old.position(**i).is_none()).copied().collect();
+
+        diff
+    }
+
+    pub fn isEmpty(&self) -> bool
+    {
+        self.added.is_empty() && self.removed.is_empty() && self.nicknames.is_empty() && self.opinions.is_empty()
+    }
+
+    /// One line per change, mercs named by their nicknames in `new`, or `old` for removed ones
+    pub fn toText(&self, old: &MercOpinions, new: &MercOpinions) -> Vec<String>
+    {
+        let name = |merc: usize| match u8::try_from(merc)
+        {
+            Ok(merc) if new.position(merc).is_some() => new.name(merc),
+            Ok(merc) => old.name(merc),
+            Err(_) => merc.to_string()
+        };
+
+        let mut lines = Vec::new();
+        for merc in &self.removed
+        {
+            lines.push(format!("Removed {} ({})", old.name(*merc), merc));
+        }
+        for merc in &self.added
+        {
+            lines.push(format!("Added {} ({})", new.name(*merc), merc));
+        }
+        for (merc, before, after) in &self.nicknames
+        {
+            lines.push(format!("Nickname of {}: {} -> {}", merc, before, after));
+        }
+        for change in &self.opinions
+        {
+            lines.push(format!("{} -> {}: {} -> {}", name(change.merc as usize), name(change.target), modifier(change.old), modifier(change.new)));
+        }
+        lines
+    }
+}
+
+/// Modifier with its sign, 0 without
+pub fn modifier(value: i32) -> String
+{
+    if value == 0 { "0".to_string() } else { format!("{:+}", value) }
+}
+
+
+//-----------------------------------------------------------------------------
+// Other tables
+//-----------------------------------------------------------------------------
+// Rows as read by csv::tableToRows, matched by the table's index field. Values a rule skips
+// when converting are the same as no value.
+
+pub fn diffRows(old: &[Vec<String>], new: &[Vec<String>], table: &TableRule) -> Vec<String>
+{
+    let index = table.index.as_str();
+    let present = |(field, value): &(String, String)| !value.is_empty()
+        && !table.rule(field).is_some_and(|(rule, _)| rule.skips(value));
+
+    let records = |rows: &[Vec<String>]| -> Vec<(String, Vec<(String, String)>)>
+    {
+        let (header, rows) = match rows.split_first()
+        {
+            Some(split) => split,
+            None => return Vec::new()
+        };
+        let column = header.iter().position(|c| c == index);
+        rows.iter().enumerate().map(|(k, row)|
+        {
+            let key = column.and_then(|c| row.get(c).cloned()).unwrap_or_else(|| format!("#{}", k + 1));
+            let fields = header.iter().cloned().zip(row.iter().cloned()).filter(present).collect();
+            (key, fields)
+        }).collect()
+    };
+    let old = records(old);
+    let new = records(new);
+
+    let mut lines = Vec::new();
+    for (key, fields) in &old
+    {
+        match new.iter().find(|(k, _)| k == key)
+        {
+            None => lines.push(format!("Removed {} {}", index, key)),
+            Some((_, newFields)) =>
+            {
+                for (field, value) in fields
+                {
+                    match newFields.iter().find(|(f, _)| f == field)
+                    {
+                        Some((_, newValue)) if newValue != value => lines.push(format!("{} {}: {}: {} -> {}", index, key, field, value, newValue)),
+                        Some(_) => {}
+                        None => lines.push(format!("{} {}: {}: {} -> (none)", index, key, field, value))
+                    }
+                }
+                for (field, value) in newFields.iter().filter(|(f, _)| !fields.iter().any(|(o, _)| o == f))
+                {
+                    lines.push(format!("{} {}: {}: (none) -> {}", index, key, field, value));
+                }
+            }
+        }
+    }
+    for (key, _) in new.iter().filter(|(k, _)| !old.iter().any(|(o, _)| o == k))
+    {
+        lines.push(format!("Added {} {}", index, key));
+    }
+    lines
+}
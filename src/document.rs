@@ -0,0 +1,436 @@
+use std::fmt;
+use std::path::Path;
+use std::io::{self, Write};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::de::{self, DeserializeOwned, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::rules::TableRule;
+use crate::createOutput;
+
+
+//-----------------------------------------------------------------------------
+// JSON and YAML documents
+//-----------------------------------------------------------------------------
+// Every table is written as { "table": <xml file name>, "records": [...] }. MercOpinions has its own
+// typed records (see mercopinions.rs), other tables use the generic records below, which follow
+// the new xml format: a field is either a text value, a list of elements with attributes or, for
+// elements with children, a map of their fields. Attributes of elements with children or text, and
+// of the record itself, are fields named "@<attribute>", the text next to them is "#text".
+//
+//   { "table": "LoadBearingEquipment.xml",
+//     "records": [ { "lbeIndex": "1", "lbeClass": "2", "LbePocket": [ { "id": "1", "pocket": "3" } ] } ] }
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format
+{
+    Xml,
+    Json,
+    Yaml
+}
+impl Format
+{
+    pub fn fromName(name: &str) -> Option<Format>
+    {
+        match name.to_ascii_lowercase().as_str()
+        {
+            "xml" => Some(Format::Xml),
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None
+        }
+    }
+
+    pub fn fromPath(filepath: &Path) -> Option<Format>
+    {
+        Format::fromName(filepath.extension()?.to_str()?)
+    }
+
+    pub fn extension(&self) -> &'static str
+    {
+        match self
+        {
+            Format::Xml => "xml",
+            Format::Json => "json",
+            Format::Yaml => "yaml"
+        }
+    }
+}
+
+
+#[derive(Serialize, Deserialize)]
+pub struct Document
+{
+    pub table: String,
+    pub records: Vec<Record>
+}
+impl Document
+{
+    /// Reads a new format xml file
+    pub fn loadXml(filepath: &Path, table: &TableRule) -> Result<Document, String>
+    {
+        let name = filepath.file_name().unwrap().to_string_lossy().into_owned();
+        let mut document = Document{table: name, records: Vec::new()};
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        // Open elements of the current record with their fields and text, the record itself first
+        let mut open: Vec<(String, Record, String)> = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(e) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e)),
+                Ok(Event::Eof) if !open.is_empty() => return Err(format!("{}: Unclosed {} at end of file", filepath.display(), open.last().unwrap().0)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) =>
+                {
+                    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                    if !open.is_empty() || name == table.record
+                    {
+                        let fields = readAttributes(element)
+                            .map_err(|e| format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e))?
+                            .into_iter()
+                            .map(|(key, value)| (format!("@{}", key), Field::Text(value)))
+                            .collect();
+                        open.push((name, Record{fields}, String::new()));
+                    }
+                }
+                Ok(Event::Text(ref e)) if !open.is_empty() =>
+                {
+                    let text = e.unescape().map_err(|e| format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e))?;
+                    open.last_mut().unwrap().2.push_str(&text);
+                }
+                Ok(Event::CData(ref e)) if !open.is_empty() =>
+                {
+                    open.last_mut().unwrap().2.push_str(&String::from_utf8_lossy(e));
+                }
+                Ok(Event::Empty(ref element)) if !open.is_empty() =>
+                {
+                    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                    let attributes = readAttributes(element)
+                        .map_err(|e| format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e))?;
+                    open.last_mut().unwrap().1.push(name, Attributes(attributes));
+                }
+                Ok(Event::End(_)) if !open.is_empty() =>
+                {
+                    let (name, mut record, text) = open.pop().unwrap();
+                    if !text.is_empty() && !record.fields.is_empty() && record.children().next().is_none()
+                    {
+                        record.fields.push(("#text".to_string(), Field::Text(text.clone())));
+                    }
+                    match open.last_mut()
+                    {
+                        None => document.records.push(record),
+                        // Elements with children or attributes are nested records, the others text fields
+                        Some((_, parent, _)) if record.fields.is_empty() => parent.fields.push((name, Field::Text(text))),
+                        Some((_, parent, _)) => parent.fields.push((name, Field::Nested(record)))
+                    }
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+
+        Ok(document)
+    }
+
+    pub fn saveXml(&self, filepath: &Path, table: &TableRule) -> Result<(), String>
+    {
+        let mut writer = createOutput(filepath)?;
+        self.writeXml(&mut writer, table)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    fn writeXml<W: Write>(&self, writer: &mut W, table: &TableRule) -> io::Result<()>
+    {
+		writeln!(writer, "<{}>", table.root)?;
+
+        for record in &self.records
+        {
+	    	writeln!(writer, "\t<{}>", startTag(&table.record, record))?;
+            writeFields(writer, record, 2)?;
+            writeln!(writer, "\t</{}>", table.record)?;
+        }
+
+		writeln!(writer, "</{}>", table.root)
+    }
+}
+
+
+fn writeFields<W: Write>(writer: &mut W, record: &Record, depth: usize) -> io::Result<()>
+{
+    let indent = "\t".repeat(depth);
+    for (tag, field) in record.children()
+    {
+        match field
+        {
+            Field::Text(value) => writeln!(writer, "{}<{}>{}</{}>", indent, tag, quick_xml::escape::partial_escape(value), tag)?,
+            Field::List(list) =>
+            {
+                for attributes in list
+                {
+                    writeln!(writer, "{}<{}{}/>", indent, tag, writeAttributes(&attributes.0))?;
+                }
+            }
+            // Text element with attributes
+            Field::Nested(nested) if nested.children().next().is_none() =>
+            {
+                let text = nested.fields.iter().find_map(|(name, field)| match field
+                {
+                    Field::Text(value) if name == "#text" => Some(value.as_str()),
+                    _ => None
+                });
+                writeln!(writer, "{}<{}>{}</{}>", indent, startTag(tag, nested), quick_xml::escape::partial_escape(text.unwrap_or_default()), tag)?;
+            }
+            Field::Nested(nested) =>
+            {
+                writeln!(writer, "{}<{}>", indent, startTag(tag, nested))?;
+                writeFields(writer, nested, depth + 1)?;
+                writeln!(writer, "{}</{}>", indent, tag)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Element name with the "@" fields of `record` as attributes
+fn startTag(tag: &str, record: &Record) -> String
+{
+    let attributes: Vec<(String, String)> = record.fields.iter()
+        .filter_map(|(name, field)| match (name.strip_prefix('@'), field)
+        {
+            (Some(key), Field::Text(value)) => Some((key.to_string(), value.clone())),
+            _ => None
+        })
+        .collect();
+    format!("{}{}", tag, writeAttributes(&attributes))
+}
+
+fn writeAttributes(attributes: &[(String, String)]) -> String
+{
+    attributes.iter()
+        .map(|(k, v)| format!(" {} = \"{}\"", k, quick_xml::escape::escape(v)))
+        .collect::<Vec<String>>()
+        .concat()
+}
+
+fn readAttributes(element: &BytesStart) -> Result<Vec<(String, String)>, quick_xml::Error>
+{
+    element.attributes()
+        .map(|a| { let a = a?; Ok((String::from_utf8_lossy(a.key.as_ref()).into_owned(), a.unescape_value()?.into_owned())) })
+        .collect()
+}
+
+
+/// Fields of one record, in file order
+pub struct Record
+{
+    pub fields: Vec<(String, Field)>
+}
+impl Record
+{
+    /// Adds an element to the list field `name`
+    pub fn push(&mut self, name: String, attributes: Attributes)
+    {
+        match self.fields.iter_mut().find(|(n, _)| *n == name)
+        {
+            Some((_, Field::List(list))) => list.push(attributes),
+            _ => self.fields.push((name, Field::List(vec![attributes])))
+        }
+    }
+
+    /// Child elements, without the attributes and text of the record's own element
+    pub fn children(&self) -> impl Iterator<Item = &(String, Field)>
+    {
+        self.fields.iter().filter(|(name, _)| !name.starts_with('@') && name != "#text")
+    }
+}
+
+pub enum Field
+{
+    Text(String),
+    List(Vec<Attributes>),
+    // Element with child elements, eg. <STAND_MODIFIERS> of Items.xml
+    Nested(Record)
+}
+
+/// Attributes of one element, in file order
+pub struct Attributes(pub Vec<(String, String)>);
+
+
+/// Table name of a JSON or YAML document, which decides how the rest of it is read
+pub fn documentTable(filepath: &Path) -> Result<String, String>
+{
+    #[derive(Deserialize)]
+    struct Header { table: String }
+
+    let header: Header = load(filepath)?;
+    Ok(header.table)
+}
+
+pub fn load<T: DeserializeOwned>(filepath: &Path) -> Result<T, String>
+{
+    let text = std::fs::read_to_string(filepath)
+        .map_err(|e| format!("Could not read file {}: {}", filepath.display(), e))?;
+    match Format::fromPath(filepath)
+    {
+        Some(Format::Json) => serde_json::from_str(&text).map_err(|e| format!("{}: {}", filepath.display(), e)),
+        Some(Format::Yaml) => serde_yaml::from_str(&text).map_err(|e| format!("{}: {}", filepath.display(), e)),
+        _ => Err(format!("{} is not a .json or .yaml file", filepath.display()))
+    }
+}
+
+pub fn save<T: Serialize>(value: &T, filepath: &Path, format: Format) -> Result<(), String>
+{
+    let text = match format
+    {
+        Format::Json => serde_json::to_string_pretty(value).map(|text| text + "\n").map_err(|e| e.to_string()),
+        Format::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        Format::Xml => Err("xml is written by the table writers, not as a document".to_string())
+    }
+    .map_err(|e| format!("Could not write {}: {}", filepath.display(), e))?;
+
+    let mut file = createOutput(filepath)?;
+    file.write_all(text.as_bytes())
+        .and_then(|_| file.flush())
+        .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+}
+
+
+//-----------------------------------------------------------------------------
+// Serde implementations
+//-----------------------------------------------------------------------------
+// Records and attributes are maps that keep their order, fields are a text or a list of attribute maps.
+// Numbers and booleans are read as text, so hand written YAML doesn't need quotes.
+
+impl Serialize for Record
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (name, field) in &self.fields
+        {
+            match field
+            {
+                Field::Text(value) => map.serialize_entry(name, value)?,
+                Field::List(list) => map.serialize_entry(name, list)?,
+                Field::Nested(record) => map.serialize_entry(name, record)?
+            }
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Attributes
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in &self.0
+        {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Record
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Record, D::Error>
+    {
+        struct RecordVisitor;
+        impl<'de> Visitor<'de> for RecordVisitor
+        {
+            type Value = Record;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("a map of fields") }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Record, A::Error>
+            {
+                let mut fields = Vec::new();
+                while let Some((name, field)) = map.next_entry::<String, Field>()?
+                {
+                    fields.push((name, field));
+                }
+                Ok(Record{fields})
+            }
+        }
+        deserializer.deserialize_map(RecordVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Attributes
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Attributes, D::Error>
+    {
+        struct AttributesVisitor;
+        impl<'de> Visitor<'de> for AttributesVisitor
+        {
+            type Value = Attributes;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("a map of attributes") }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Attributes, A::Error>
+            {
+                let mut attributes = Vec::new();
+                while let Some((name, value)) = map.next_entry::<String, Text>()?
+                {
+                    attributes.push((name, value.0));
+                }
+                Ok(Attributes(attributes))
+            }
+        }
+        deserializer.deserialize_map(AttributesVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Field
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Field, D::Error>
+    {
+        struct FieldVisitor;
+        impl<'de> Visitor<'de> for FieldVisitor
+        {
+            type Value = Field;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str("a text value, a list of attribute maps or a map of fields") }
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Field, E> { Ok(Field::Text(value.to_string())) }
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Field, E> { Ok(Field::Text(value.to_string())) }
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Field, E> { Ok(Field::Text(value.to_string())) }
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Field, E> { Ok(Field::Text(value.to_string())) }
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Field, E> { Ok(Field::Text((value as u8).to_string())) }
+            fn visit_unit<E: de::Error>(self) -> Result<Field, E> { Ok(Field::Text(String::new())) }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Field, A::Error>
+            {
+                let mut list = Vec::new();
+                while let Some(attributes) = seq.next_element::<Attributes>()?
+                {
+                    list.push(attributes);
+                }
+                Ok(Field::List(list))
+            }
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Field, A::Error>
+            {
+                Record::deserialize(de::value::MapAccessDeserializer::new(map)).map(Field::Nested)
+            }
+        }
+        deserializer.deserialize_any(FieldVisitor)
+    }
+}
+
+// Attribute value, numbers and booleans are taken as text
+struct Text(String);
+impl<'de> Deserialize<'de> for Text
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Text, D::Error>
+    {
+        match Field::deserialize(deserializer)?
+        {
+            Field::Text(value) => Ok(Text(value)),
+            _ => Err(de::Error::custom("expected a text value, not a list or map"))
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::ops::RangeInclusive;
+
+use crate::mercopinions::{MercOpinions, setOpinion};
+
+
+//-----------------------------------------------------------------------------
+// Edit scripts
+//-----------------------------------------------------------------------------
+// One edit per line or separated by ;, # starts a comment:
+//
+//   set   <mercs> -> <targets> <modifier>     opinion of each merc about each target
+//   add   <mercs> -> <targets> <modifier>     added to the current opinion
+//   scale <mercs> -> <targets> <factor>       current opinion times factor, rounded
+//   mirror <mercs> [-> <targets>]             targets get the opinion the mercs have about them
+//
+// Mercs and targets are comma separated nicknames or uiIndices, * for every merc in the file, or aim
+// for the A.I.M. mercs, whose uiIndices come from the aim range of the MercOpinions rules. Nicknames with spaces are written in quotes. Opinions of a merc about
+// themselves are never changed.
+//
+//   add aim -> Mike -10
+//   mirror Gus
+//   set "Fox",Shadow -> Ivan,Igor 5; scale * -> * 0.5
+
+#[derive(Clone, Copy)]
+pub enum Operation
+{
+    Set(i32),
+    Add(i32),
+    Scale(f32),
+    Mirror
+}
+
+pub enum Selector
+{
+    All,
+    Aim(RangeInclusive<u8>),
+    Mercs(Vec<u8>)
+}
+impl Selector
+{
+    fn parse(text: &str, mercOpinions: &MercOpinions, aim: &RangeInclusive<u8>) -> Result<Selector, String>
+    {
+        match text
+        {
+            "*" => Ok(Selector::All),
+            "aim" | "AIM" => Ok(Selector::Aim(aim.clone())),
+            _ =>
+            {
+                let mercs = splitList(text).iter()
+                    .map(|merc| mercOpinions.findMerc(merc))
+                    .collect::<Result<Vec<u8>, String>>()?;
+                Ok(Selector::Mercs(mercs))
+            }
+        }
+    }
+
+    pub fn mercs(&self, mercOpinions: &MercOpinions) -> Vec<u8>
+    {
+        match self
+        {
+            Selector::All => mercOpinions.index.clone(),
+            Selector::Aim(aim) => mercOpinions.index.iter().filter(|i| aim.contains(i)).copied().collect(),
+            Selector::Mercs(mercs) => mercs.clone()
+        }
+    }
+}
+
+pub struct Edit
+{
+    pub line: usize,
+    pub operation: Operation,
+    pub mercs: Selector,
+    pub targets: Selector
+}
+
+
+/// Parses a script, nicknames are looked up in `mercOpinions` and aim selects the mercs in `aim`
+pub fn parseScript(script: &str, mercOpinions: &MercOpinions, aim: &RangeInclusive<u8>) -> Result<Vec<Edit>, String>
+{
+    let mut edits = Vec::new();
+    for (line, text) in script.lines().enumerate()
+    {
+        let text = text.split('#').next().unwrap();
+        for statement in text.split(';').filter(|s| !s.trim().is_empty())
+        {
+            let edit = parseEdit(statement, line + 1, mercOpinions, aim)
+                .map_err(|e| format!("Line {}: {}", line + 1, e))?;
+            edits.push(edit);
+        }
+    }
+    Ok(edits)
+}
+
+fn parseEdit(statement: &str, line: usize, mercOpinions: &MercOpinions, aim: &RangeInclusive<u8>) -> Result<Edit, String>
+{
+    let words = splitWords(&statement.replace("->", " -> "));
+    let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+
+    let (name, words) = words.split_first().ok_or("Empty edit")?;
+    let (mercs, rest) = words.split_first().ok_or_else(|| format!("{} needs the mercs to edit", name))?;
+    let (targets, rest) = match rest
+    {
+        ["->", targets, rest @ ..] => (*targets, rest),
+        _ if *name == "mirror" => ("*", rest),
+        _ => return Err(format!("{} needs -> and the target mercs", name))
+    };
+
+    let number = || match rest
+    {
+        [value] => Ok(*value),
+        _ => Err(format!("{} needs one value after the target mercs", name))
+    };
+    let operation = match *name
+    {
+        "set" => Operation::Set(number()?.parse().map_err(|_| format!("Invalid modifier {}", number().unwrap()))?),
+        "add" => Operation::Add(number()?.parse().map_err(|_| format!("Invalid modifier {}", number().unwrap()))?),
+        "scale" => Operation::Scale(number()?.parse().map_err(|_| format!("Invalid factor {}", number().unwrap()))?),
+        "mirror" if rest.is_empty() => Operation::Mirror,
+        "mirror" => return Err("mirror takes no value".to_string()),
+        _ => return Err(format!("Unknown edit {}, use set, add, scale or mirror", name))
+    };
+
+    Ok(Edit{
+        line,
+        operation,
+        mercs: Selector::parse(mercs, mercOpinions, aim)?,
+        targets: Selector::parse(targets, mercOpinions, aim)?
+    })
+}
+
+/// Applies the edits in order. Returns the number of opinions that changed.
+pub fn applyEdits(mercOpinions: &mut MercOpinions, edits: &[Edit]) -> Result<u32, String>
+{
+    let mut changed = 0;
+    for edit in edits
+    {
+        let targets = edit.targets.mercs(mercOpinions);
+        for merc in edit.mercs.mercs(mercOpinions)
+        {
+            for target in targets.iter().filter(|t| **t != merc)
+            {
+                // mirror writes into the target's record
+                let (holder, about) = match edit.operation
+                {
+                    Operation::Mirror => (*target, merc),
+                    _ => (merc, *target)
+                };
+                let k = mercOpinions.position(holder)
+                    .ok_or_else(|| format!("Line {}: merc {} has no record in the file", edit.line, holder))?;
+
+                let current = mercOpinions.opinions[k].get(about as usize).copied().unwrap_or(0);
+                let value = match edit.operation
+                {
+                    Operation::Set(value) => value,
+                    Operation::Add(value) => current.saturating_add(value),
+                    Operation::Scale(factor) => (current as f32 * factor).round() as i32,
+                    Operation::Mirror => mercOpinions.position(merc)
+                        .and_then(|m| mercOpinions.opinions[m].get(*target as usize).copied())
+                        .unwrap_or(0)
+                };
+                // The game stores opinions as INT8
+                if value < i8::MIN as i32 || value > i8::MAX as i32
+                {
+                    return Err(format!("Line {}: opinion of {} about {} would be {}, outside {} to {}",
+                        edit.line, mercOpinions.name(holder), mercOpinions.name(about), value, i8::MIN, i8::MAX));
+                }
+                if value != current
+                {
+                    setOpinion(&mut mercOpinions.opinions[k], about as usize, value);
+                    changed += 1;
+                }
+            }
+        }
+    }
+    Ok(changed)
+}
+
+
+/// Words separated by whitespace, "quoted" words may contain spaces
+fn splitWords(text: &str) -> Vec<String>
+{
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in text.chars()
+    {
+        match c
+        {
+            '"' => { quoted = !quoted; word.push(c); }
+            c if c.is_whitespace() && !quoted =>
+            {
+                if !word.is_empty() { words.push(std::mem::take(&mut word)); }
+            }
+            _ => word.push(c)
+        }
+    }
+    if !word.is_empty() { words.push(word); }
+    words
+}
+
+/// Comma separated list, quotes removed
+fn splitList(text: &str) -> Vec<String>
+{
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut quoted = false;
+    for c in text.chars()
+    {
+        match c
+        {
+            '"' => quoted = !quoted,
+            ',' if !quoted => items.push(std::mem::take(&mut item)),
+            _ => item.push(c)
+        }
+    }
+    items.push(item);
+    items.into_iter().filter(|i| !i.is_empty()).collect()
+}
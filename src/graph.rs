@@ -0,0 +1,86 @@
+use std::path::Path;
+use std::io::Write;
+use std::ops::RangeInclusive;
+
+use crate::createOutput;
+use crate::mercopinions::MercOpinions;
+
+
+/// Which opinions end up in the graph
+pub struct GraphFilter
+{
+    /// Smallest |modifier| that is drawn
+    pub minModifier: i32,
+    /// Only opinions between these mercs, the A.I.M. mercs of the rules (TableRule::aimMercs)
+    pub aim: Option<RangeInclusive<u8>>,
+    /// Only opinions of and about this merc
+    pub merc: Option<u8>
+}
+impl GraphFilter
+{
+    pub fn new() -> GraphFilter
+    {
+        GraphFilter{minModifier: 1, aim: None, merc: None}
+    }
+
+    fn accepts(&self, from: u8, to: u8, modifier: i32) -> bool
+    {
+        modifier != 0
+            && modifier.abs() >= self.minModifier
+            && self.aim.as_ref().is_none_or(|aim| aim.contains(&from) && aim.contains(&to))
+            && self.merc.is_none_or(|m| m == from || m == to)
+    }
+}
+
+
+//-----------------------------------------------------------------------------
+// Graphviz DOT
+//-----------------------------------------------------------------------------
+// Mercs are nodes labelled with their nickname, opinions are edges from the merc holding the
+// opinion to its target. Liking is green, disliking red, stronger opinions are darker and thicker.
+
+pub fn opinionsToDot(mercOpinions: &MercOpinions, filter: &GraphFilter) -> String
+{
+    let mut edges = Vec::new();
+    for (k, from) in mercOpinions.index.iter().enumerate()
+    {
+        for (j, modifier) in mercOpinions.opinions[k].iter().enumerate()
+        {
+            let to = match u8::try_from(j) { Ok(to) => to, Err(_) => continue };
+            if filter.accepts(*from, to, *modifier)
+            {
+                edges.push((*from, to, *modifier));
+            }
+        }
+    }
+    let strongest = edges.iter().map(|(_, _, m)| m.abs()).max().unwrap_or(1);
+
+    let mut nodes: Vec<u8> = edges.iter().flat_map(|(from, to, _)| [*from, *to]).chain(filter.merc).collect();
+    nodes.sort();
+    nodes.dedup();
+
+    let mut dot = String::new();
+    dot.push_str("digraph MercOpinions {\n");
+    dot.push_str("\tnode [shape = box, style = rounded];\n");
+    for node in nodes
+    {
+        let label = mercOpinions.name(node);
+        dot.push_str(&format!("\tm{} [label = \"{}\"];\n", node, label.replace('\\', "\\\\").replace('"', "\\\"")));
+    }
+    for (from, to, modifier) in edges
+    {
+        let intensity = modifier.abs() as f32 / strongest as f32;
+        let hue = if modifier > 0 { 0.333 } else { 0.0 };
+        dot.push_str(&format!("\tm{} -> m{} [label = \"{}\", color = \"{:.3} {:.3} {:.3}\", penwidth = {:.1}];\n",
+            from, to, modifier, hue, 0.25 + 0.75 * intensity, 0.9 - 0.3 * intensity, 1.0 + 2.0 * intensity));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+pub fn saveDot(dot: &str, filepath: &Path) -> Result<(), String>
+{
+    let mut writer = createOutput(filepath)?;
+    writer.write_all(dot.as_bytes()).and_then(|_| writer.flush())
+        .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+}
@@ -0,0 +1,188 @@
+use std::path::Path;
+use std::io::{self, BufReader, Write};
+use std::fs::File;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::rules::TableRule;
+use crate::items::Items;
+use crate::{createOutput, parseString, parseu8, parseu16};
+
+
+// Legacy dealer inventories have one record per fixed slot with an ubItemIndex/ubOptimalNumber pair.
+// The newer layout lists only the stocked items inside a single record.
+pub struct NPCInventory
+{
+    pub items: Vec<u16>,
+    pub optimal: Vec<u8>,
+    // Slot and tag of every field with a value the newer layout has no place for
+    pub dropped: Vec<(usize, String, String)>
+}
+impl NPCInventory
+{
+    pub const FILE: &'static str = "*Inventory.xml";
+    /// Element of a stocked item in the newer layout
+    pub const ITEM: &'static str = "AnItem";
+
+    pub fn new() -> NPCInventory
+    {
+        let items = Vec::new();
+        let optimal = Vec::new();
+        let dropped = Vec::new();
+
+        NPCInventory{items, optimal, dropped}
+    }
+
+    pub fn loadNPCInventory(filepath: &Path, table: &TableRule) -> Result<NPCInventory, String>
+    {
+        let mut inventory = NPCInventory::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(element) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), element)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) if element.name().as_ref() == table.record.as_bytes() =>
+                {
+                    inventory.readItem(&mut reader, &mut buf, table)
+                        .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+        Ok(inventory)
+    }
+
+
+    pub fn saveNPCInventory(&self, filepath: &Path, table: &TableRule) -> Result<(), String>
+    {
+        let mut writer = createOutput(filepath)?;
+        self.writeXml(&mut writer, table)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    fn writeXml<W: Write>(&self, writer: &mut W, table: &TableRule) -> io::Result<()>
+    {
+		writeln!(writer, "<{}>", table.root)?;
+		writeln!(writer, "\t<{}>", table.record)?;
+
+        for i in 0..self.items.len()
+        {
+            // Empty slots
+            if self.items[i] == 0 { continue; }
+
+            writeln!(writer, "\t\t<{} index = \"{}\" optimal = \"{}\"/>", NPCInventory::ITEM, self.items[i], self.optimal[i])?;
+        }
+
+		writeln!(writer, "\t</{}>", table.record)?;
+		writeln!(writer, "</{}>", table.root)
+    }
+
+    /// Returns a warning for every stocked item that does not exist in Items.xml.
+    pub fn checkItems(&self, items: &Items) -> Vec<String>
+    {
+        let mut warnings = Vec::new();
+
+        for (slot, item) in self.items.iter().enumerate()
+        {
+            if *item != 0 && !items.contains(*item)
+            {
+                warnings.push(format!("slot {}: ubItemIndex {} does not exist in {}", slot, item, Items::FILE));
+            }
+        }
+
+        warnings
+    }
+
+    /// Returns a warning for every value that isn't written to the newer layout: fields other than
+    /// ubItemIndex and ubOptimalNumber, and stock numbers of empty slots.
+    pub fn warnings(&self) -> Vec<String>
+    {
+        let mut warnings = Vec::new();
+
+        for (slot, tag, value) in &self.dropped
+        {
+            warnings.push(format!("slot {}: {} {} is dropped, the converted inventory only keeps item and optimal number", slot, tag, value));
+        }
+        for (slot, item) in self.items.iter().enumerate()
+        {
+            if *item == 0 && self.optimal[slot] != 0
+            {
+                warnings.push(format!("slot {}: ubOptimalNumber {} of an empty slot is dropped", slot, self.optimal[slot]));
+            }
+        }
+
+        warnings
+    }
+
+    pub fn readItem(&mut self, reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, table: &TableRule) -> Result<(), String>
+	{
+		self.items.push(0);
+		self.optimal.push(0);
+		// A converted file lists all its items in one record, each gets a slot of its own
+		let mut converted = false;
+		loop
+		{
+			match reader.read_event_into(buf)
+			{
+				    Ok(Event::Start(e)) =>
+				    {
+					        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+					        match e.name().as_ref()
+					        {
+			            		b"ubItemIndex" => { *self.items.last_mut().unwrap() = parseu16(reader, buf, &name)?; }
+			            		b"ubOptimalNumber" => { *self.optimal.last_mut().unwrap() = parseu8(reader, buf, &name)?; }
+								_ =>
+								{
+									let value = parseString(reader, buf, name.as_bytes())?;
+									if !value.is_empty() && value != "0"
+									{
+										self.dropped.push((self.items.len() - 1, name, value));
+									}
+								}
+						        }
+				    }
+
+				    Ok(Event::Empty(e)) if e.name().as_ref() == NPCInventory::ITEM.as_bytes() =>
+				    {
+					        if converted
+					        {
+						        self.items.push(0);
+						        self.optimal.push(0);
+					        }
+					        converted = true;
+					        *self.items.last_mut().unwrap() = parseAttribute(&e, "index")
+						        .map_err(|e| format!("{} at position {}", e, reader.buffer_position()))?;
+					        *self.optimal.last_mut().unwrap() = parseAttribute(&e, "optimal")
+						        .map_err(|e| format!("{} at position {}", e, reader.buffer_position()))?;
+				    }
+
+				    Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+				    Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", table.record)),
+				    Ok(Event::End(ref element)) if element.name().as_ref() == table.record.as_bytes() => break,
+				    _ => (),
+			}
+			buf.clear();
+		}
+		Ok(())
+	}
+}
+
+// Value of attribute `key` of an AnItem element, 0 if it has none
+fn parseAttribute<T: std::str::FromStr + Default>(element: &BytesStart, key: &str) -> Result<T, String>
+{
+    let value = match element.try_get_attribute(key).map_err(|e| format!("{:?}", e))?
+    {
+        Some(attribute) => attribute.unescape_value().map_err(|e| format!("{:?}", e))?.into_owned(),
+        None => return Ok(T::default())
+    };
+    value.trim().parse().map_err(|_| format!("Invalid {} {} = \"{}\"", NPCInventory::ITEM, key, value))
+}
@@ -0,0 +1,88 @@
+use std::path::Path;
+use std::io::BufReader;
+use std::fs::File;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::{parseString, parseu16};
+
+
+// Only the fields other tables refer to are loaded
+pub struct Items
+{
+    pub index: Vec<u16>,
+    pub names: Vec<String>
+}
+impl Items
+{
+    pub const FILE: &'static str = "Items.xml";
+
+    pub fn new() -> Items
+    {
+        let index = Vec::new();
+        let names = Vec::new();
+
+        Items{index, names}
+    }
+
+    pub fn loadItems(filepath: &Path) -> Result<Items, String>
+    {
+        let mut items = Items::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(element) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), element)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) if element.name().as_ref() == b"ITEM" =>
+                {
+                    items.readItem(&mut reader, &mut buf)
+                        .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+        Ok(items)
+    }
+
+    pub fn contains(&self, index: u16) -> bool
+    {
+        self.index.contains(&index)
+    }
+
+    pub fn readItem(&mut self, reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>) -> Result<(), String>
+	{
+		self.index.push(0);
+		self.names.push(String::new());
+		loop
+		{
+			match reader.read_event_into(buf)
+			{
+				    Ok(Event::Start(e)) =>
+				    {
+					        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+					        match e.name().as_ref()
+					        {
+			            		b"uiIndex" => { *self.index.last_mut().unwrap() = parseu16(reader, buf, &name)?; }
+			            		b"szItemName" => { *self.names.last_mut().unwrap() = parseString(reader, buf, b"szItemName")?; }
+								_ => {}
+						        }
+				    }
+
+				    Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+				    Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", "ITEM")),
+				    Ok(Event::End(ref element)) if element.name().as_ref() == b"ITEM" => break,
+				    _ => (),
+			}
+			buf.clear();
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,352 @@
+use std::path::Path;
+use std::io::{self, BufReader, Write};
+use std::fs::File;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::rules::{Rule, TableRule};
+use crate::{createOutput, parseString, parseu8, parseu16};
+
+
+/// lbeClass values (eLBE_CLASS in the 1.13 source) with the pType values of Pockets.xml an
+/// item of the class can hold. pType 0 are the fixed pockets of the inventory itself and 2 the
+/// restricted ones (gun slings, knife sheaths), which only the single pocket classes carry.
+pub const LBE_CLASSES: [(u8, &str, &[u16]); 6] = [
+    (1, "thigh pack", &[1]),
+    (2, "vest", &[1]),
+    (3, "combat pack", &[1]),
+    (4, "backpack", &[1]),
+    (5, "LBE pocket", &[1, 2]),
+    (6, "other pocket", &[1, 2])
+];
+
+/// Pocket slots of an LBE item (MAX_ITEMS_IN_LBE in the 1.13 source), lbePocketIndex0 to 11
+pub const MAX_POCKETS: usize = 12;
+
+
+pub struct LoadBearingEquipment
+{
+    pub index: Vec<u16>,
+    pub classes: Vec<u8>,
+    pub volumes: Vec<u8>,
+    // Fields other than the pockets in their original order, copied to the output as they are
+    pub fields: Vec<Vec<(String, String)>>,
+    pub pockets: Vec<Vec<u16>>,
+    // Position in `fields` the pockets are written at, where the first pocket tag was
+    pub pocketsAt: Vec<usize>
+}
+impl LoadBearingEquipment
+{
+    pub const FILE: &'static str = "LoadBearingEquipment.xml";
+
+    pub fn new() -> LoadBearingEquipment
+    {
+        let index = Vec::new();
+        let classes = Vec::new();
+        let volumes = Vec::new();
+        let fields = Vec::new();
+        let pockets = Vec::new();
+        let pocketsAt = Vec::new();
+
+        LoadBearingEquipment{index, classes, volumes, fields, pockets, pocketsAt}
+    }
+
+    pub fn loadLoadBearingEquipment(filepath: &Path, table: &TableRule) -> Result<LoadBearingEquipment, String>
+    {
+        let mut lbe = LoadBearingEquipment::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(element) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), element)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) if element.name().as_ref() == table.record.as_bytes() =>
+                {
+                    lbe.readItem(&mut reader, &mut buf, table)
+                        .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+        Ok(lbe)
+    }
+
+
+    pub fn saveLoadBearingEquipment(&self, filepath: &Path, table: &TableRule) -> Result<(), String>
+    {
+        let rule = table.rules.first()
+            .ok_or_else(|| format!("Conversion rules for {} have no pocket rule", table.file))?;
+
+        let mut writer = createOutput(filepath)?;
+        self.writeXml(&mut writer, table, rule)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    fn writeXml<W: Write>(&self, writer: &mut W, table: &TableRule, rule: &Rule) -> io::Result<()>
+    {
+		writeln!(writer, "<{}>", table.root)?;
+
+        for i in 0..self.index.len()
+        {
+	    	writeln!(writer, "\t<{}>", table.record)?;
+
+            for (k, (tag, value)) in self.fields[i].iter().enumerate()
+            {
+                if k == self.pocketsAt[i]
+                {
+                    self.writePockets(writer, i, rule)?;
+                }
+                write_tag_s!(writer, value, tag, true)?;
+            }
+            if self.pocketsAt[i] >= self.fields[i].len()
+            {
+                self.writePockets(writer, i, rule)?;
+            }
+
+            writeln!(writer, "\t</{}>", table.record)?;
+        }
+
+		writeln!(writer, "</{}>", table.root)
+    }
+
+    fn writePockets<W: Write>(&self, writer: &mut W, i: usize, rule: &Rule) -> io::Result<()>
+    {
+        for (j, value) in self.pockets[i].iter().enumerate()
+        {
+            let value = value.to_string();
+            if !rule.skips(&value)
+            {
+                writeln!(writer, "\t\t<{}/>", rule.render(j as u32, &value))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Pocket `n` of the last item, the pockets are written where the first one was
+    fn setPocket(&mut self, n: u32, value: u16) -> Result<(), String>
+    {
+        let n = n as usize;
+        if n >= MAX_POCKETS
+        {
+            return Err(format!("Pocket slot {} is outside the {} slots of an LBE item", n, MAX_POCKETS));
+        }
+        let fields = self.fields.last().unwrap().len();
+        let pocketsAt = self.pocketsAt.last_mut().unwrap();
+        *pocketsAt = fields.min(*pocketsAt);
+        let row = self.pockets.last_mut().unwrap();
+        if row.len() <= n { row.resize(n + 1, 0); }
+        row[n] = value;
+        Ok(())
+    }
+
+    fn setField(&mut self, name: String, value: String)
+    {
+        match name.as_str()
+        {
+            "lbeIndex" => *self.index.last_mut().unwrap() = parseNumber(&value, &name),
+            "lbeClass" => *self.classes.last_mut().unwrap() = parseNumber(&value, &name),
+            "lbeAvailableVolume" => *self.volumes.last_mut().unwrap() = parseNumber(&value, &name),
+            _ => {}
+        }
+        self.fields.last_mut().unwrap().push((name, value));
+    }
+
+    /// Cross-checks the pocket references against Pockets.xml. Returns a warning per problem found.
+    pub fn checkPockets(&self, pockets: &Pockets) -> Vec<String>
+    {
+        let mut warnings = Vec::new();
+
+        for i in 0..self.index.len()
+        {
+            let class = LBE_CLASSES.iter().find(|(class, _, _)| *class == self.classes[i]);
+            if class.is_none()
+            {
+                warnings.push(format!("LBE {}: lbeClass {} is not a known LBE class", self.index[i], self.classes[i]));
+            }
+
+            let mut volume: u32 = 0;
+            for (slot, pocket) in self.pockets[i].iter().enumerate()
+            {
+                if *pocket == 0 { continue; }
+                match pockets.index.iter().position(|p| p == pocket)
+                {
+                    Some(p) =>
+                    {
+                        volume += pockets.volumes[p] as u32;
+                        if let Some((_, name, types)) = class
+                        {
+                            if !types.contains(&pockets.types[p])
+                            {
+                                warnings.push(format!("LBE {} ({}): lbePocketIndex{} references pocket {} ({}) of pType {} which doesn't fit a {}",
+                                    self.index[i], name, slot, pocket, pockets.names[p], pockets.types[p], name));
+                            }
+                        }
+                    }
+                    None => warnings.push(format!("LBE {} (class {}): lbePocketIndex{} references pocket {} which does not exist in {}",
+                        self.index[i], self.classes[i], slot, pocket, Pockets::FILE))
+                }
+            }
+
+            // Items without lbeAvailableVolume predate the volume based pocket system
+            if self.volumes[i] > 0 && volume > self.volumes[i] as u32
+            {
+                warnings.push(format!("LBE {} (class {}): pockets need volume {} but lbeAvailableVolume is {}",
+                    self.index[i], self.classes[i], volume, self.volumes[i]));
+            }
+        }
+
+        warnings
+    }
+
+    pub fn readItem(&mut self, reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, table: &TableRule) -> Result<(), String>
+	{
+		self.index.push(0);
+		self.classes.push(0);
+		self.volumes.push(0);
+		self.fields.push(Vec::new());
+		self.pockets.push(Vec::new());
+		self.pocketsAt.push(usize::MAX);
+		loop
+		{
+			match reader.read_event_into(buf)
+			{
+				    Ok(Event::Start(e)) =>
+				    {
+					        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+					        // lbePocketIndex{n} tags, numbered by the pocket slot
+					        if let Some((_, n)) = table.rule(&name)
+					        {
+						        let value = parseu16(reader, buf, &name)?;
+						        self.setPocket(n, value)?;
+					        }
+					        // LbePocket elements of an already converted file
+					        else if let Some((n, value)) = table.rules.iter().find_map(|rule| rule.matchTarget(&e))
+					        {
+						        self.setPocket(n, parseNumber(&value, &name))?;
+					        }
+					        else
+					        {
+						        let value = parseString(reader, buf, name.as_bytes())?;
+						        self.setField(name, value);
+					        }
+				    }
+				    Ok(Event::Empty(e)) =>
+				    {
+					        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+					        match table.rules.iter().find_map(|rule| rule.matchTarget(&e))
+					        {
+						        Some((n, value)) => self.setPocket(n, parseNumber(&value, &name))?,
+						        None => self.setField(name, String::new())
+					        }
+				    }
+
+				    Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+				    Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", table.record)),
+				    Ok(Event::End(ref element)) if element.name().as_ref() == table.record.as_bytes() => break,
+				    _ => (),
+			}
+			buf.clear();
+		}
+		Ok(())
+	}
+}
+
+
+pub struct Pockets
+{
+    pub index: Vec<u16>,
+    pub names: Vec<String>,
+    pub types: Vec<u16>,
+    pub volumes: Vec<u8>
+}
+impl Pockets
+{
+    pub const FILE: &'static str = "Pockets.xml";
+
+    pub fn new() -> Pockets
+    {
+        let index = Vec::new();
+        let names = Vec::new();
+        let types = Vec::new();
+        let volumes = Vec::new();
+
+        Pockets{index, names, types, volumes}
+    }
+
+    pub fn loadPockets(filepath: &Path) -> Result<Pockets, String>
+    {
+        let mut pockets = Pockets::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(element) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), element)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) if element.name().as_ref() == b"POCKET" =>
+                {
+                    pockets.readItem(&mut reader, &mut buf)
+                        .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+        Ok(pockets)
+    }
+
+    pub fn readItem(&mut self, reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>) -> Result<(), String>
+	{
+		self.index.push(0);
+		self.names.push(String::new());
+		self.types.push(1);
+		self.volumes.push(0);
+		loop
+		{
+			match reader.read_event_into(buf)
+			{
+				    Ok(Event::Start(e)) =>
+				    {
+					        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+					        match e.name().as_ref()
+					        {
+			            		b"pIndex" => { *self.index.last_mut().unwrap() = parseu16(reader, buf, &name)?; }
+			            		b"pName" => { *self.names.last_mut().unwrap() = parseString(reader, buf, b"pName")?; }
+			            		b"pType" => { *self.types.last_mut().unwrap() = parseu16(reader, buf, &name)?; }
+			            		b"pVolume" => { *self.volumes.last_mut().unwrap() = parseu8(reader, buf, &name)?; }
+								_ => {}
+						        }
+				    }
+
+				    Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+				    Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", "POCKET")),
+				    Ok(Event::End(ref element)) if element.name().as_ref() == b"POCKET" => break,
+				    _ => (),
+			}
+			buf.clear();
+		}
+		Ok(())
+	}
+}
+
+fn parseNumber<T: std::str::FromStr + Default>(value: &str, name: &str) -> T
+{
+    match value.trim().parse()
+    {
+        Ok(value) => value,
+        Err(_) => { println!("Error parsing value {} for tag {}", value, name); Default::default() }
+    }
+}
@@ -0,0 +1,153 @@
+#![allow(non_snake_case)]
+#![allow(clippy::new_without_default)]
+
+use std::path::Path;
+use std::io::{BufReader, BufWriter};
+use std::fs::File;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+//-----------------------------------------------------------------------------
+// Macros
+//-----------------------------------------------------------------------------
+#[macro_export]
+macro_rules! write_tag_i {
+	($file:tt, $value:tt, $tag:tt, $forcewrite:tt) => {{
+		
+		let empty = $value == 0;
+
+		if !empty || $forcewrite
+		{
+			writeln!($file, "\t\t<{}>{}</{}>", $tag, $value, $tag)
+		}
+		else { Ok(()) }
+	}}
+}
+#[macro_export]
+macro_rules! write_tag_s {
+	($file:tt, $value:tt, $tag:tt, $forcewrite:tt) => {{
+		
+		let empty = $value.is_empty();
+
+		if !empty || $forcewrite
+		{
+			let s: String = if $value.contains(['&', '<', '>'])
+			{ $value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;") }
+			else { $value.clone() };
+
+			writeln!($file, "\t\t<{}>{}</{}>", $tag, s, $tag)
+		}
+		else { Ok(()) }
+	}}
+}
+
+pub mod rules;
+pub mod mercopinions;
+pub mod lbe;
+pub mod items;
+pub mod inventory;
+pub mod profiles;
+pub mod profdat;
+pub mod weapons;
+pub mod localized;
+pub mod strings;
+pub mod csv;
+pub mod document;
+pub mod database;
+pub mod graph;
+pub mod analysis;
+pub mod lint;
+pub mod references;
+pub mod diff;
+pub mod merge;
+pub mod patch;
+pub mod edit;
+pub mod query;
+pub mod schema;
+pub mod watch;
+
+
+//-----------------------------------------------------------------------------
+// Functions
+//-----------------------------------------------------------------------------
+/// Creates the output file and the folders it is in. Its path is printed, like every written file.
+pub fn createOutput(filepath: &Path) -> Result<BufWriter<File>, String>
+{
+	println!("{}", filepath.display());
+	if let Some(parent) = filepath.parent()
+	{
+		std::fs::create_dir_all(parent).map_err(|e| format!("Could not create folder {}: {}", parent.display(), e))?;
+	}
+	File::create(filepath)
+		.map(BufWriter::new)
+		.map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+}
+
+pub fn parseString(reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, tag: &[u8]) -> Result<String, String>
+{
+	loop {
+		match reader.read_event_into(buf) 
+		{
+			Ok(Event::Text(e)) => {
+				let value = e.unescape().map_err(|e| format!("Error at position {}: {:?}", reader.buffer_position(), e))?.into_owned();
+				return Ok(value);
+			}
+			Ok(Event::End(ref element)) if element.name().as_ref() == tag => break,
+			Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", String::from_utf8_lossy(tag))),
+			Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+			_ => {}
+		}
+	}
+
+	Ok("".to_string())
+}
+
+pub fn parsebool(reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, name: &str) -> Result<bool, String>
+{
+	loop {
+		match reader.read_event_into(buf) 
+		{
+			Ok(Event::Text(e)) => {
+				let value = e.unescape().map_err(|e| format!("Error at position {}: {:?}", reader.buffer_position(), e))?.into_owned().parse::<u32>();
+				match value
+				{
+					Ok(value) => {return Ok(value != 0);}
+					_ => {println!("Error parsing value for tag {}", name); return Ok(false);}
+				}
+			}
+			Ok(Event::End(ref element)) if element.name().as_ref() == name.as_bytes() => return Ok(false),
+			Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", name)),
+			Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+			_ => {}
+		}
+	}
+}
+
+macro_rules! parsers {
+	($($name:ident, $type:ty),*) => {
+		
+		$(pub fn $name(reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, name: &str) -> Result<$type, String>
+		{
+			loop {
+				match reader.read_event_into(buf) 
+				{
+					Ok(Event::Text(e)) => {
+						let value = e.unescape().map_err(|e| format!("Error at position {}: {:?}", reader.buffer_position(), e))?.into_owned().parse::<$type>();
+						match value
+						{
+							Ok(value) => {return Ok(value);}
+							_ => {println!("Error parsing value for tag {} at position {}", name, reader.buffer_position()); return Ok(Default::default());}
+						}
+					}
+					// Empty element, its text isn't the next element's
+					Ok(Event::End(ref element)) if element.name().as_ref() == name.as_bytes() => return Ok(Default::default()),
+					Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", name)),
+					Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+					_ => {}
+				}
+			}
+		})*
+	};
+}
+parsers!(parseu8, u8, parsei8, i8, parseu16, u16, parsei16, i16, parseu32, u32, parsei32, i32, parseu64, u64, parsei64, i64, parsef32, f32);
+
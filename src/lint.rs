@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::mercopinions::MercOpinions;
+use crate::profiles::MercProfiles;
+
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity
+{
+    Off,
+    Info,
+    Warning,
+    Error
+}
+impl Severity
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Severity::Off => "off",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        }
+    }
+}
+
+
+//-----------------------------------------------------------------------------
+// Rules
+//-----------------------------------------------------------------------------
+// id, name, default severity, description. Rules can be referred to by id or name in the config.
+pub const LINT_RULES: &[(&str, &str, Severity, &str)] = &[
+    ("MO001", "unknown-target", Severity::Error, "Opinion about an index that has no profile"),
+    ("MO002", "self-opinion", Severity::Warning, "Merc has an opinion about themselves"),
+    ("MO003", "modifier-range", Severity::Error, "Modifier outside the range the game accepts"),
+    ("MO004", "empty-nickname", Severity::Warning, "Merc has no nickname"),
+    ("MO005", "duplicate-nickname", Severity::Error, "Nickname used by more than one merc"),
+    ("MO006", "profile-count", Severity::Warning, "Number of records differs from the number of profiles"),
+];
+
+// Positions in LINT_RULES
+const UNKNOWN_TARGET: usize = 0;
+const SELF_OPINION: usize = 1;
+const MODIFIER_RANGE: usize = 2;
+const EMPTY_NICKNAME: usize = 3;
+const DUPLICATE_NICKNAME: usize = 4;
+const PROFILE_COUNT: usize = 5;
+
+
+/// Lint config file, eg.
+///
+///   [severity]
+///   self-opinion = "off"
+///   MO004 = "info"
+///
+///   [modifiers]
+///   min = -50
+///   max = 50
+///
+///   [[suppress]]
+///   rule = "MO002"
+///   merc = 12
+#[derive(Deserialize)]
+pub struct LintConfig
+{
+    #[serde(default)]
+    pub severity: BTreeMap<String, Severity>,
+    #[serde(default)]
+    pub modifiers: ModifierRange,
+    #[serde(default)]
+    pub suppress: Vec<Suppression>
+}
+impl LintConfig
+{
+    pub fn new() -> LintConfig
+    {
+        LintConfig{severity: BTreeMap::new(), modifiers: ModifierRange::default(), suppress: Vec::new()}
+    }
+
+    pub fn load(filepath: &Path) -> Result<LintConfig, String>
+    {
+        let text = std::fs::read_to_string(filepath)
+            .map_err(|e| format!("Could not read lint config {}: {}", filepath.display(), e))?;
+        let config: LintConfig = toml::from_str(&text)
+            .map_err(|e| format!("Error parsing lint config {}: {}", filepath.display(), e))?;
+
+        for rule in config.severity.keys().chain(config.suppress.iter().map(|s| &s.rule))
+        {
+            if !LINT_RULES.iter().any(|(id, name, _, _)| id == rule || name == rule)
+            {
+                return Err(format!("Unknown lint rule {} in {}", rule, filepath.display()));
+            }
+        }
+        Ok(config)
+    }
+
+    fn severity(&self, id: &str, name: &str, default: Severity) -> Severity
+    {
+        self.severity.get(id).or_else(|| self.severity.get(name)).copied().unwrap_or(default)
+    }
+
+    fn suppressed(&self, id: &str, name: &str, merc: Option<u8>) -> bool
+    {
+        self.suppress.iter().any(|s| (s.rule == id || s.rule == name) && (s.merc.is_none() || s.merc == merc))
+    }
+}
+
+// Opinions are a signed byte in the game's profile data
+#[derive(Deserialize)]
+pub struct ModifierRange
+{
+    pub min: i32,
+    pub max: i32
+}
+impl Default for ModifierRange
+{
+    fn default() -> ModifierRange
+    {
+        ModifierRange{min: i8::MIN as i32, max: i8::MAX as i32}
+    }
+}
+
+/// Silences a rule, for one merc or for all of them
+#[derive(Deserialize)]
+pub struct Suppression
+{
+    pub rule: String,
+    pub merc: Option<u8>
+}
+
+
+#[derive(Serialize)]
+pub struct Finding
+{
+    pub rule: &'static str,
+    pub name: &'static str,
+    pub severity: Severity,
+    pub merc: Option<u8>,
+    pub message: String
+}
+
+
+/// Machine readable output of a lint run
+#[derive(Serialize)]
+pub struct LintReport
+{
+    pub file: String,
+    pub findings: Vec<Finding>
+}
+
+
+/// Checks `mercOpinions` against the profiles, or against its own records without them
+pub fn lintMercOpinions(mercOpinions: &MercOpinions, profiles: Option<&MercProfiles>, config: &LintConfig) -> Vec<Finding>
+{
+    let mut found: Vec<(usize, Option<u8>, String)> = Vec::new();
+    let known = |uiIndex: u8| match profiles
+    {
+        Some(profiles) => profiles.index.contains(&uiIndex),
+        None => mercOpinions.position(uiIndex).is_some()
+    };
+
+    for (k, merc) in mercOpinions.index.iter().enumerate()
+    {
+        let name = mercOpinions.name(*merc);
+        for (j, modifier) in mercOpinions.opinions[k].iter().enumerate()
+        {
+            if *modifier == 0 { continue; }
+
+            if !u8::try_from(j).is_ok_and(known)
+            {
+                found.push((UNKNOWN_TARGET, Some(*merc), format!("{} has an opinion about {}, which has no profile", name, j)));
+            }
+            if j == *merc as usize
+            {
+                found.push((SELF_OPINION, Some(*merc), format!("{} has an opinion of {:+} about themselves", name, modifier)));
+            }
+            if *modifier < config.modifiers.min || *modifier > config.modifiers.max
+            {
+                found.push((MODIFIER_RANGE, Some(*merc), format!("{} has an opinion of {} about {}, outside {} to {}",
+                    name, modifier, j, config.modifiers.min, config.modifiers.max)));
+            }
+        }
+
+        let nickname = &mercOpinions.nicknames[k];
+        if nickname.trim().is_empty()
+        {
+            found.push((EMPTY_NICKNAME, Some(*merc), format!("Merc {} has no nickname", merc)));
+        }
+        else if let Some(first) = mercOpinions.nicknames[..k].iter().position(|n| n.eq_ignore_ascii_case(nickname))
+        {
+            found.push((DUPLICATE_NICKNAME, Some(*merc), format!("Merc {} has the nickname {}, which merc {} has too", merc, nickname, mercOpinions.index[first])));
+        }
+    }
+
+    if let Some(profiles) = profiles
+    {
+        if profiles.index.len() != mercOpinions.index.len()
+        {
+            found.push((PROFILE_COUNT, None, format!("{} records, but {} has {} profiles", mercOpinions.index.len(), MercProfiles::FILE, profiles.index.len())));
+        }
+    }
+
+    let mut findings = Vec::new();
+    for (rule, merc, message) in found
+    {
+        let (id, name, default, _) = LINT_RULES[rule];
+        let severity = config.severity(id, name, default);
+        if severity != Severity::Off && !config.suppressed(id, name, merc)
+        {
+            findings.push(Finding{rule: id, name, severity, merc, message});
+        }
+    }
+    findings
+}
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+
+use crate::rules::TableRule;
+
+
+// Localized TableData files mirror the base file with translated strings, named with a language prefix
+pub const LANGUAGES: &[&str] = &["German", "Russian", "Polish", "French", "Italian", "Dutch", "Chinese"];
+
+
+/// Splits "German.Items.xml" into the language and the base file name "Items.xml"
+pub fn splitLanguage(filename: &str) -> (Option<&'static str>, &str)
+{
+    if let Some((prefix, base)) = filename.split_once('.')
+    {
+        if let Some(language) = LANGUAGES.iter().find(|l| l.eq_ignore_ascii_case(prefix))
+        {
+            if !base.is_empty() { return (Some(language), base); }
+        }
+    }
+    (None, filename)
+}
+
+/// Path of the base file next to a localized file, None for base files
+pub fn basePath(filepath: &Path) -> Option<PathBuf>
+{
+    let filename = filepath.file_name()?.to_str()?;
+    match splitLanguage(filename)
+    {
+        (Some(_), base) => Some(filepath.with_file_name(base)),
+        (None, _) => None
+    }
+}
+
+/// Compares the record indices of a localized file with its base file. Returns a warning per
+/// record that is missing from the translation, has no record in the base file or is translated
+/// more than once.
+pub fn checkTranslation(filepath: &Path, basepath: &Path, table: &TableRule) -> Result<Vec<String>, String>
+{
+    let mut warnings = Vec::new();
+    let translated = table.readIndices(filepath)?;
+    let base = table.readIndices(basepath)?;
+    let baseSet: HashSet<&String> = base.iter().collect();
+    let mut translatedSet: HashSet<&String> = HashSet::new();
+    let mut duplicates: HashSet<&String> = HashSet::new();
+    for index in &translated
+    {
+        if !translatedSet.insert(index) && duplicates.insert(index)
+        {
+            warnings.push(format!("{} {} is translated more than once", table.index, index));
+        }
+    }
+
+    for index in &base
+    {
+        if !translatedSet.contains(index)
+        {
+            warnings.push(format!("{} {} is missing from the translation", table.index, index));
+        }
+    }
+    for index in &translated
+    {
+        if !baseSet.contains(index)
+        {
+            warnings.push(format!("{} {} is not in {}", table.index, index, basepath.display()));
+        }
+    }
+
+    Ok(warnings)
+}
@@ -2,54 +2,31 @@
 
 use std::env;
 use std::process;
-use std::path::PathBuf;
-use std::io::{BufReader, Write};
-use std::str;
-use std::fs::File;
-use quick_xml::events::Event;
-use quick_xml::Reader;
-
-//-----------------------------------------------------------------------------
-// Macros
-//-----------------------------------------------------------------------------
-macro_rules! write_tag_i {
-	($file:tt, $value:tt, $tag:tt, $forcewrite:tt) => {{
-		
-		let mut empty = false;
-		if $value == 0 {empty = true;}
-
-		if empty == false || $forcewrite == true
-		{
-			match write!($file, "\t\t<{}>{}</{}>\n", $tag, $value, $tag)
-			{
-				Ok(_) => {}
-				Err(e) => {panic!("Error writing value {} for xml tag {}\n {:?}", $value, $tag, e)}
-			}
-		}
-	}}
-}
-macro_rules! write_tag_s {
-	($file:tt, $value:tt, $tag:tt, $forcewrite:tt) => {{
-		
-		let mut empty = false;
-		if $value == "" {empty = true;}
-
-		if empty == false || $forcewrite == true
-		{
-			let s: String;
-			if $value.contains("&")
-			{ s = $value.replace("&", "&amp;"); }
-			else { s = $value.clone(); }
-
-			match write!($file, "\t\t<{}>{}</{}>\n", $tag, s, $tag)
-			{
-				Ok(_) => {}
-				Err(e) => {panic!("Error writing value {} for xml tag {}\n {:?}", $value, $tag, e)}
-			}
-		}
-	}}
-}
-
+use std::thread;
+use std::path::{Path, PathBuf};
+use JA2_ConvertXMLData::rules::{RuleSet, TableRule};
+use JA2_ConvertXMLData::mercopinions::{MercOpinions, MercOpinionsDocument};
+use JA2_ConvertXMLData::lbe::{LoadBearingEquipment, Pockets};
+use JA2_ConvertXMLData::items::Items;
+use JA2_ConvertXMLData::inventory::NPCInventory;
+use JA2_ConvertXMLData::profiles::MercProfiles;
+use JA2_ConvertXMLData::profdat::ProfDat;
+use JA2_ConvertXMLData::weapons::Weapons;
+use JA2_ConvertXMLData::strings::{self, Translations};
+use JA2_ConvertXMLData::document::{self, Document, Format};
+use JA2_ConvertXMLData::database::Database;
+use JA2_ConvertXMLData::graph::{self, GraphFilter};
+use JA2_ConvertXMLData::analysis::Report;
+use JA2_ConvertXMLData::references::ReferenceSet;
+use JA2_ConvertXMLData::diff::{self, OpinionDiff};
+use JA2_ConvertXMLData::merge::Merge;
+use JA2_ConvertXMLData::patch::OpinionPatch;
+use JA2_ConvertXMLData::edit;
+use JA2_ConvertXMLData::query;
+use JA2_ConvertXMLData::schema::{self, TableSchema};
+use JA2_ConvertXMLData::watch::{self, Watcher};
+use JA2_ConvertXMLData::lint::{self, LintConfig, LintReport, Severity};
+use JA2_ConvertXMLData::{localized, csv};
 
 
 fn main() {
@@ -59,504 +36,1189 @@ fn main() {
         process::exit(1)
     });
 
-    let xmlpath = PathBuf::from(config.xmlfilepath);
-    if xmlpath.exists() == false {
-        println!("xml file not found at: {}", xmlpath.to_string_lossy());
-        process::exit(4);
+    for path in &config.paths {
+        if !PathBuf::from(path).exists() {
+            println!("xml file not found at: {}", path);
+            process::exit(4);
+        }
+    }
+
+    let mut rules = match &config.rulesfilepath {
+        Some(rulespath) => RuleSet::withUserRules(&PathBuf::from(rulespath)).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(5)
+        }),
+        None => RuleSet::builtin(),
+    };
+    if let Some(table) = rules.tables.iter_mut().find(|t| t.matches(MercOpinions::FILE)) {
+        table.comments |= config.comments;
+        table.symbolic |= config.symbolic;
     }
 
-    let data = MercOpinions::loadMercOpinions(&xmlpath);
-    let mut pathOout = xmlpath.clone();
-    pathOout.pop();
-    pathOout.push("MercOpinions out.xml");
-    data.saveMercOpinions(&pathOout);
+    match config.command {
+        Command::Convert => convert(&config, &rules),
+        Command::InjectOpinions => injectOpinions(&config, &rules),
+        Command::ExtractOpinions => extractOpinions(&config, &rules),
+        Command::ImportProf => importProf(&config, &rules),
+        Command::ExportProf => exportProf(&config, &rules),
+        Command::ExtractStrings => extractStrings(&config, &rules),
+        Command::InjectStrings => injectStrings(&config, &rules),
+        Command::ExportCsv => exportCsv(&config, &rules),
+        Command::ImportCsv => importCsv(&config, &rules),
+        Command::ExportDb => exportDb(&config, &rules),
+        Command::ImportDb => importDb(&config, &rules),
+        Command::ExportGraph => exportGraph(&config, &rules),
+        Command::Analyze => analyze(&config, &rules),
+        Command::Lint => lintOpinions(&config, &rules),
+        Command::CheckRefs => checkRefs(&config),
+        Command::Diff => diffTables(&config, &rules),
+        Command::Merge => mergeOpinions(&config, &rules),
+        Command::MakePatch => makePatch(&config, &rules),
+        Command::ApplyPatch => applyPatch(&config, &rules),
+        Command::Edit => editOpinions(&config, &rules),
+        Command::Query => queryOpinions(&config, &rules),
+        Command::Schema => writeSchemas(&config, &rules),
+        Command::Validate => validate(&config, &rules),
+        Command::Watch => watchDirectory(&config, &rules),
+    }
+}
+
+
+// --format for commands that write tables, xml if not given
+fn outputFormat(config: &Config) -> Format {
+    match config.format.as_deref() {
+        None => Format::Xml,
+        Some(name) => Format::fromName(name).unwrap_or_else(|| {
+            println!("Unknown format {}, use xml, json or yaml", name);
+            process::exit(1)
+        }),
+    }
+}
+
+
+fn convert(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let format = outputFormat(config);
+
+    if xmlpath.is_dir() {
+        let outdir = match &config.outpath {
+            Some(outdir) => PathBuf::from(outdir),
+            None => xmlpath.join("Converted"),
+        };
+        convertDirectory(&xmlpath, &outdir, rules, format);
+        return;
+    }
+
+    // JSON and YAML documents name their table
+    let table = match Format::fromPath(&xmlpath) {
+        Some(Format::Json | Format::Yaml) => {
+            let name = document::documentTable(&xmlpath).unwrap_or_else(|err| {
+                println!("{}", err);
+                process::exit(7)
+            });
+            rules.table(&name).unwrap_or_else(|| {
+                println!("No conversion rules for {}", name);
+                process::exit(6)
+            })
+        }
+        _ => tableFor(rules, &xmlpath),
+    };
+
+    let pathOout = match &config.outpath {
+        Some(outdir) => PathBuf::from(outdir).join(xmlpath.file_name().unwrap()),
+        None => table.outputPath(&xmlpath),
+    };
+    convertFileAs(&xmlpath, &pathOout.with_extension(format.extension()), table, xmlpath.parent().unwrap(), format).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+}
+
+
+fn mercOpinionsTable(rules: &RuleSet) -> &TableRule {
+    rules.table(MercOpinions::FILE).unwrap_or_else(|| {
+        println!("No conversion rules for {}", MercOpinions::FILE);
+        process::exit(6)
+    })
+}
+
+
+// Exits with 7 when symbolic targets can't be resolved
+fn loadOpinions(xmlpath: &Path, table: &TableRule) -> MercOpinions {
+    MercOpinions::loadMercOpinions(xmlpath, table).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    })
+}
+
+
+fn saveOpinions(mercOpinions: &MercOpinions, pathOout: &Path, table: &TableRule) {
+    mercOpinions.saveMercOpinions(pathOout, table).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    })
+}
+
+
+fn loadProfiles(profilespath: &Path) -> MercProfiles {
+    MercProfiles::loadMercProfiles(profilespath).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    })
+}
+
+
+fn injectOpinions(config: &Config, rules: &RuleSet) {
+    let profilespath = PathBuf::from(&config.paths[0]);
+    let opinionspath = PathBuf::from(&config.paths[1]);
+    let table = mercOpinionsTable(rules);
+    let rule = table.rules.first().unwrap_or_else(|| {
+        println!("Conversion rules for {} have no opinion rule", table.file);
+        process::exit(6)
+    });
+
+    let mercOpinions = loadOpinions(&opinionspath, table);
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => profilespath.with_file_name(MercProfiles::FILE.replace(".xml", " out.xml")),
+    };
+    MercProfiles::injectOpinions(&profilespath, &pathOout, &mercOpinions, rule).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+}
+
+
+fn extractOpinions(config: &Config, rules: &RuleSet) {
+    let profilespath = PathBuf::from(&config.paths[0]);
+    let table = mercOpinionsTable(rules);
+
+    let profiles = loadProfiles(&profilespath);
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => profilespath.with_file_name(MercOpinions::FILE.replace(".xml", " out.xml")),
+    };
+    saveOpinions(&profiles.toMercOpinions(), &pathOout, table);
+}
+
+
+fn loadProfDat(path: &Path) -> ProfDat {
+    ProfDat::loadProfDat(path).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    })
+}
+
+
+fn importProf(config: &Config, rules: &RuleSet) {
+    let profpath = PathBuf::from(&config.paths[0]);
+    let table = mercOpinionsTable(rules);
+
+    let profDat = loadProfDat(&profpath);
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => profpath.with_file_name(MercOpinions::FILE.replace(".xml", " out.xml")),
+    };
+    saveOpinions(&profDat.toMercOpinions(), &pathOout, table);
+}
+
+
+// Writes the opinions back into a copy of an existing Prof.dat, the rest of the records stay as they were
+fn exportProf(config: &Config, rules: &RuleSet) {
+    let profpath = PathBuf::from(&config.paths[0]);
+    let opinionspath = PathBuf::from(&config.paths[1]);
+    let table = mercOpinionsTable(rules);
+
+    let mut profDat = loadProfDat(&profpath);
+    let mercOpinions = loadOpinions(&opinionspath, table);
+    profDat.applyMercOpinions(&mercOpinions);
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => profpath.with_file_name(ProfDat::FILE.replace(".dat", " out.dat")),
+    };
+    profDat.saveProfDat(&pathOout);
+}
+
+
+fn tableFor<'a>(rules: &'a RuleSet, xmlpath: &Path) -> &'a TableRule {
+    rules.forFile(xmlpath).unwrap_or_else(|| {
+        println!("No conversion rules for {}", xmlpath.to_string_lossy());
+        process::exit(6)
+    })
+}
+
+// Converted files can be named like the output, eg. "MercOpinions out.xml"
+fn outputTableFor<'a>(rules: &'a RuleSet, xmlpath: &Path) -> &'a TableRule {
+    rules.forOutput(xmlpath).unwrap_or_else(|| {
+        println!("No conversion rules for {}", xmlpath.to_string_lossy());
+        process::exit(6)
+    })
+}
+
+
+fn extractStrings(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let table = tableFor(rules, &xmlpath);
+
+    // Localized files are paired with their base file, which provides the source texts
+    let basepath = localized::basePath(&xmlpath).filter(|p| p.exists());
+    let translations = Translations::extractStrings(&xmlpath, basepath.as_deref(), table).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+
+    // --format decides the format, the extension of --out only when it isn't given
+    let xliff = match config.format.as_deref() {
+        Some("xliff") => true,
+        Some("po") => false,
+        Some(_) => {
+            println!("{}", Command::ExtractStrings.usage());
+            process::exit(1)
+        }
+        None => config.outpath.as_ref().is_some_and(|outpath| strings::isXliff(Path::new(outpath))),
+    };
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => xmlpath.with_extension(if xliff { "xlf" } else { "po" }),
+    };
+    translations.save(&pathOout, xliff).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+}
+
+
+fn injectStrings(config: &Config, rules: &RuleSet) {
+    let stringspath = PathBuf::from(&config.paths[0]);
+    let xmlpath = PathBuf::from(&config.paths[1]);
+    let table = tableFor(rules, &xmlpath);
+
+    let translations = Translations::load(&stringspath).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => table.outputPath(&xmlpath),
+    };
+    translations.injectStrings(&xmlpath, &pathOout, table).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+}
+
+
+fn loadRows(xmlpath: &Path, table: &TableRule) -> Vec<Vec<String>> {
+    csv::tableToRows(xmlpath, table).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    })
+}
+
+
+fn exportCsv(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let table = tableFor(rules, &xmlpath);
+
+    let rows = if table.file.eq_ignore_ascii_case(MercOpinions::FILE) {
+        csv::opinionsToRows(&loadOpinions(&xmlpath, table))
+    } else {
+        loadRows(&xmlpath, table)
+    };
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => xmlpath.with_extension("csv"),
+    };
+    csv::saveCsv(&rows, &pathOout).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+}
+
+
+// The table is taken from the csv name, eg. MercOpinions.csv is imported as MercOpinions.xml
+fn importCsv(config: &Config, rules: &RuleSet) {
+    let csvpath = PathBuf::from(&config.paths[0]);
+    let xmlpath = csvpath.with_extension("xml");
+    let table = tableFor(rules, &xmlpath);
+
+    let rows = csv::loadCsv(&csvpath).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => table.outputPath(&xmlpath),
+    };
+
+    if table.file.eq_ignore_ascii_case(MercOpinions::FILE) {
+        let mercOpinions = csv::opinionsFromRows(&rows).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(7)
+        });
+        saveOpinions(&mercOpinions, &pathOout, table);
+    } else {
+        // Rows are written out in the old format and converted like any other file
+        let tempdir = env::temp_dir().join(format!("JA2-ConvertXMLData-{}", process::id()));
+        let temppath = tempdir.join(xmlpath.file_name().unwrap());
+        std::fs::create_dir_all(&tempdir).unwrap();
+        std::fs::write(&temppath, csv::rowsToXml(&rows, table)).unwrap();
+        let result = convertFile(&temppath, &pathOout, table, csvpath.parent().unwrap());
+        let _ = std::fs::remove_dir_all(&tempdir);
+        result.unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(7)
+        });
+    }
+}
+
+
+fn databaseError(err: String) -> ! {
+    println!("{}", err);
+    process::exit(7)
+}
+
+
+// Writes every table of a TableData folder, or a single file, into one SQLite database
+fn exportDb(config: &Config, rules: &RuleSet) {
+    let inpath = PathBuf::from(&config.paths[0]);
+    let (dirpath, mut entries) = if inpath.is_dir() {
+        let entries: Vec<PathBuf> = match std::fs::read_dir(&inpath) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect(),
+            Err(e) => {
+                println!("Could not read directory {}: {}", inpath.display(), e);
+                process::exit(4)
+            }
+        };
+        (inpath.clone(), entries)
+    } else {
+        (inpath.parent().unwrap().to_path_buf(), vec![inpath.clone()])
+    };
+    entries.sort();
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => dirpath.join(Database::FILE),
+    };
+    let mut database = Database::create(&pathOout).unwrap_or_else(|e| databaseError(e));
+
+    for xmlpath in entries {
+        let table = match rules.forFile(&xmlpath) {
+            Some(table) => table,
+            None => continue,
+        };
+        let file = xmlpath.file_name().unwrap().to_string_lossy().into_owned();
+        let result = if file.eq_ignore_ascii_case(MercOpinions::FILE) {
+            database.writeMercOpinions(&loadOpinions(&xmlpath, table))
+        } else {
+            database.writeRows(&file, &loadRows(&xmlpath, table))
+        };
+        result.unwrap_or_else(|e| databaseError(e));
+        println!("{}", file);
+    }
+    println!("{}", &pathOout.to_str().unwrap());
+}
+
+
+// Writes every table in the database back to a new format xml file
+fn importDb(config: &Config, rules: &RuleSet) {
+    let dbpath = PathBuf::from(&config.paths[0]);
+    let datadir = dbpath.parent().unwrap();
+    let outdir = match &config.outpath {
+        Some(outdir) => PathBuf::from(outdir),
+        None => datadir.join("Converted"),
+    };
+
+    let database = Database::open(&dbpath).unwrap_or_else(|e| databaseError(e));
+    let tables = database.tables().unwrap_or_else(|e| databaseError(e));
+
+    let tempdir = env::temp_dir().join(format!("JA2-ConvertXMLData-{}", process::id()));
+    for (name, file) in tables {
+        let table = match rules.table(&file) {
+            Some(table) => table,
+            None => {
+                println!("Warning: no conversion rules for {}, table {} was skipped", file, name);
+                continue;
+            }
+        };
+        let pathOout = outdir.join(&file);
+
+        if file.eq_ignore_ascii_case(MercOpinions::FILE) {
+            let mercOpinions = database.readMercOpinions().unwrap_or_else(|e| databaseError(e));
+            saveOpinions(&mercOpinions, &pathOout, table);
+        } else {
+            // Rows are written out in the old format and converted like any other file
+            let rows = database.readRows(&name).unwrap_or_else(|e| databaseError(e));
+            let temppath = tempdir.join(&file);
+            std::fs::create_dir_all(&tempdir).unwrap();
+            std::fs::write(&temppath, csv::rowsToXml(&rows, table)).unwrap();
+            if let Err(err) = convertFile(&temppath, &pathOout, table, datadir) {
+                let _ = std::fs::remove_dir_all(&tempdir);
+                println!("{}", err);
+                process::exit(7)
+            }
+        }
+    }
+    let _ = std::fs::remove_dir_all(&tempdir);
+}
+
+
+fn exportGraph(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let table = mercOpinionsTable(rules);
+    let mercOpinions = loadOpinions(&xmlpath, table);
+
+    let mut filter = GraphFilter::new();
+    if let Some(minModifier) = config.minModifier {
+        filter.minModifier = minModifier;
+    }
+    if config.aimOnly {
+        filter.aim = Some(table.aimMercs());
+    }
+    if let Some(merc) = &config.merc {
+        filter.merc = Some(mercOpinions.findMerc(merc).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(1)
+        }));
+    }
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => xmlpath.with_extension("dot"),
+    };
+    graph::saveDot(&graph::opinionsToDot(&mercOpinions, &filter), &pathOout).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+}
+
+
+// Prints the report, or writes it to the --out file
+fn analyze(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let table = mercOpinionsTable(rules);
+    let report = Report::analyze(&loadOpinions(&xmlpath, table));
+
+    let text = match config.format.as_deref() {
+        Some("json") => serde_json::to_string_pretty(&report).unwrap() + "\n",
+        _ => report.toText(),
+    };
+    match &config.outpath {
+        Some(outpath) => {
+            println!("{}", outpath);
+            std::fs::write(outpath, text).unwrap();
+        }
+        None => print!("{}", text),
+    }
+}
+
+
+// Exits with 8 when there are findings with error severity, so CI jobs fail on them.
+// MercProfiles.xml next to the file is used for the profile checks unless --profiles names another one.
+fn lintOpinions(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let table = mercOpinionsTable(rules);
+    let mercOpinions = loadOpinions(&xmlpath, table);
+
+    let lintConfig = match &config.configpath {
+        Some(configpath) => LintConfig::load(&PathBuf::from(configpath)).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(5)
+        }),
+        None => LintConfig::new(),
+    };
+    let profilespath = match &config.profilespath {
+        Some(profilespath) => Some(PathBuf::from(profilespath)),
+        None => Some(xmlpath.with_file_name(MercProfiles::FILE)).filter(|p| p.exists()),
+    };
+    let profiles = profilespath.map(|p| loadProfiles(&p));
+
+    let report = LintReport {
+        file: xmlpath.file_name().unwrap().to_string_lossy().into_owned(),
+        findings: lint::lintMercOpinions(&mercOpinions, profiles.as_ref(), &lintConfig),
+    };
+    match config.format.as_deref() {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        _ => {
+            for finding in &report.findings {
+                println!("{}: {} {} {}: {}", report.file, finding.severity.name(), finding.rule, finding.name, finding.message);
+            }
+        }
+    }
+
+    if report.findings.iter().any(|f| f.severity == Severity::Error) {
+        process::exit(8);
+    }
+}
+
+
+// Exits with 8 when there are broken references, and with 7 when a file can't be read
+fn checkRefs(config: &Config) {
+    let dirpath = PathBuf::from(&config.paths[0]);
+    if !dirpath.is_dir() {
+        println!("{}", Command::CheckRefs.usage());
+        process::exit(1);
+    }
+
+    let (broken, notes) = ReferenceSet::builtin().and_then(|references| references.checkDirectory(&dirpath)).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+    for note in &notes {
+        println!("Note: {}", note);
+    }
+    for reference in &broken {
+        println!("{}", reference);
+    }
+    println!("{} broken references", broken.len());
+
+    if !broken.is_empty() {
+        process::exit(8);
+    }
+}
+
+
+// Both files go through the loader of the first file's table, so legacy and new format files compare
+fn diffTables(config: &Config, rules: &RuleSet) {
+    let oldpath = PathBuf::from(&config.paths[0]);
+    let newpath = PathBuf::from(&config.paths[1]);
+    let table = tableFor(rules, &oldpath);
+
+    let lines = if table.file.eq_ignore_ascii_case(MercOpinions::FILE) {
+        let old = loadOpinions(&oldpath, table);
+        let new = loadOpinions(&newpath, table);
+        OpinionDiff::diffMercOpinions(&old, &new).toText(&old, &new)
+    } else {
+        diff::diffRows(&loadRows(&oldpath, table), &loadRows(&newpath, table), table)
+    };
+
+    for line in &lines {
+        println!("{}", line);
+    }
+    if lines.is_empty() {
+        println!("No differences");
+    }
+}
+
+
+// Conflicting cells take our value, or theirs with --prefer theirs. Exits with 8 when there were conflicts.
+fn mergeOpinions(config: &Config, rules: &RuleSet) {
+    let basepath = PathBuf::from(&config.paths[0]);
+    let ourspath = PathBuf::from(&config.paths[1]);
+    let theirspath = PathBuf::from(&config.paths[2]);
+    let table = mercOpinionsTable(rules);
+
+    let base = loadOpinions(&basepath, table);
+    let ours = loadOpinions(&ourspath, table);
+    let theirs = loadOpinions(&theirspath, table);
+    let merge = Merge::mergeMercOpinions(&base, &ours, &theirs, config.prefer.as_deref() == Some("theirs"));
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => ourspath.with_file_name(MercOpinions::FILE.replace(".xml", " merged.xml")),
+    };
+    saveOpinions(&merge.merged, &pathOout, table);
+
+    for conflict in &merge.conflicts {
+        println!("Conflict: {}", conflict.message);
+    }
+    if !merge.conflicts.is_empty() {
+        println!("{} conflicts, resolved with {} values", merge.conflicts.len(), config.prefer.as_deref().unwrap_or("ours"));
+        process::exit(8);
+    }
+}
+
+
+fn makePatch(config: &Config, rules: &RuleSet) {
+    let basepath = PathBuf::from(&config.paths[0]);
+    let modpath = PathBuf::from(&config.paths[1]);
+    let table = mercOpinionsTable(rules);
+
+    let base = loadOpinions(&basepath, table);
+    let modded = loadOpinions(&modpath, table);
+    let patch = OpinionPatch::makePatch(&base, &modded);
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => {
+            let format = outputFormat(config);
+            modpath.with_file_name(format!("MercOpinions.patch.{}", format.extension()))
+        }
+    };
+    patch.save(&pathOout).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+    println!("{} records patched", patch.records.len());
+}
+
+
+fn applyPatch(config: &Config, rules: &RuleSet) {
+    let basepath = PathBuf::from(&config.paths[0]);
+    let patchpath = PathBuf::from(&config.paths[1]);
+    let table = mercOpinionsTable(rules);
+
+    let base = loadOpinions(&basepath, table);
+    let patch = OpinionPatch::load(&patchpath).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+    let patched = patch.applyPatch(&base).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(7)
+    });
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => basepath.with_file_name(MercOpinions::FILE.replace(".xml", " patched.xml")),
+    };
+    saveOpinions(&patched, &pathOout, table);
+}
+
+
+// Edits come from --expr, or from the script file given with --script
+fn editOpinions(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let table = mercOpinionsTable(rules);
+    let mut mercOpinions = loadOpinions(&xmlpath, table);
+
+    let script = match (&config.expression, &config.scriptpath) {
+        (Some(expression), None) => expression.clone(),
+        (None, Some(scriptpath)) => std::fs::read_to_string(scriptpath).unwrap_or_else(|e| {
+            println!("Could not read script {}: {}", scriptpath, e);
+            process::exit(4)
+        }),
+        _ => {
+            println!("{}", Command::Edit.usage());
+            process::exit(1)
+        }
+    };
+
+    let edits = edit::parseScript(&script, &mercOpinions, &table.aimMercs()).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(1)
+    });
+    let changed = edit::applyEdits(&mut mercOpinions, &edits).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(1)
+    });
+
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => table.outputPath(&xmlpath),
+    };
+    saveOpinions(&mercOpinions, &pathOout, table);
+    println!("{} opinions changed", changed);
+}
+
+
+// Legacy and new format files are both read by loadOpinions
+fn queryOpinions(config: &Config, rules: &RuleSet) {
+    let xmlpath = PathBuf::from(&config.paths[0]);
+    let table = mercOpinionsTable(rules);
+    let mercOpinions = loadOpinions(&xmlpath, table);
+
+    let find = |merc: &str| mercOpinions.findMerc(merc).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(1)
+    });
+    let lines = match (&config.merc, &config.pair) {
+        (Some(merc), None) => query::queryMerc(&mercOpinions, find(merc)),
+        (None, Some((a, b))) => query::queryPair(&mercOpinions, find(a), find(b)),
+        _ => {
+            println!("{}", Command::Query.usage());
+            process::exit(1)
+        }
+    };
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+
+// A folder gets the schemas of every table, written to the Schemas folder in it unless --out names another
+fn writeSchemas(config: &Config, rules: &RuleSet) {
+    let path = PathBuf::from(&config.paths[0]);
+    let json = config.format.as_deref() == Some("json");
+    let extension = if json { "schema.json" } else { "xsd" };
+    let text = |table: &TableRule| {
+        let schema = TableSchema::fromTable(table);
+        match json {
+            true => serde_json::to_string_pretty(&schema.toJsonSchema()).unwrap() + "\n",
+            false => schema.toXsd(),
+        }
+    };
+
+    if path.is_dir() {
+        let outdir = match &config.outpath {
+            Some(outdir) => PathBuf::from(outdir),
+            None => path.join("Schemas"),
+        };
+        for table in &rules.tables {
+            let name = TableSchema::fromTable(table).name();
+            schema::saveSchema(&text(table), &outdir.join(format!("{}.{}", name, extension)));
+        }
+        return;
+    }
+
+    let table = outputTableFor(rules, &path);
+    let pathOout = match &config.outpath {
+        Some(outpath) => PathBuf::from(outpath),
+        None => path.with_file_name(format!("{}.{}", TableSchema::fromTable(table).name(), extension)),
+    };
+    schema::saveSchema(&text(table), &pathOout);
+}
+
+
+// Checks new format files against the schema of their table. Exits with 8 when a file is invalid.
+fn validate(config: &Config, rules: &RuleSet) {
+    let path = PathBuf::from(&config.paths[0]);
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let mut entries: Vec<PathBuf> = match std::fs::read_dir(&path) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect(),
+            Err(e) => {
+                println!("Could not read directory {}: {}", path.display(), e);
+                process::exit(4)
+            }
+        };
+        entries.sort();
+        entries.into_iter().filter(|p| Format::fromPath(p) == Some(Format::Xml) && rules.forOutput(p).is_some()).collect()
+    } else {
+        outputTableFor(rules, &path);
+        vec![path]
+    };
+
+    let mut invalid = 0;
+    for xmlpath in &files {
+        let table = rules.forOutput(xmlpath).unwrap();
+        let errors = TableSchema::fromTable(table).validate(xmlpath).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(7)
+        });
+        for error in &errors {
+            println!("{}", error);
+        }
+        if !errors.is_empty() {
+            invalid += 1;
+        }
+    }
+    println!("{} of {} files valid", files.len() - invalid, files.len());
+
+    if invalid > 0 {
+        process::exit(8);
+    }
+}
+
+
+// Reconverts legacy files of the folder as they change, until stopped. Errors in a file are printed
+// instead of ending the watch.
+fn watchDirectory(config: &Config, rules: &RuleSet) {
+    let dirpath = PathBuf::from(&config.paths[0]);
+    if !dirpath.is_dir() {
+        println!("{}", Command::Watch.usage());
+        process::exit(1);
+    }
+    let format = outputFormat(config);
+    let outdir = match &config.outpath {
+        Some(outdir) => PathBuf::from(outdir),
+        None => dirpath.join("Converted"),
+    };
+    if outdir.canonicalize().is_ok_and(|o| dirpath.canonicalize().is_ok_and(|d| o == d)) {
+        println!("--out can't be the watched folder, converted files would overwrite their sources");
+        process::exit(1);
+    }
+
+    let watched = |path: &Path| Format::fromPath(path) == Some(Format::Xml) && rules.forFile(path).is_some();
+    let mut watcher = Watcher::new(&dirpath, &watched).unwrap_or_else(|err| {
+        println!("{}", err);
+        process::exit(4)
+    });
+    println!("Watching {} files in {}, converted files go to {}", watcher.files(), dirpath.display(), outdir.display());
+
+    loop {
+        thread::sleep(watch::INTERVAL);
+        let changed = watcher.poll(&watched).unwrap_or_else(|err| {
+            println!("{}", err);
+            process::exit(4)
+        });
+        for xmlpath in changed {
+            let table = rules.forFile(&xmlpath).unwrap();
+            println!("{} changed", xmlpath.file_name().unwrap().to_string_lossy());
+
+            let pathOout = outdir.join(xmlpath.file_name().unwrap()).with_extension(format.extension());
+            if let Err(err) = convertFileAs(&xmlpath, &pathOout, table, &dirpath, format) {
+                println!("{}", err);
+            }
+        }
+    }
+}
+
+
+// Converts every file in `dirpath` that has conversion rules. Outputs keep the source file names.
+fn convertDirectory(dirpath: &Path, outdir: &Path, rules: &RuleSet, format: Format) {
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(dirpath) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect(),
+        Err(e) => {
+            println!("Could not read directory {}: {}", dirpath.display(), e);
+            process::exit(4)
+        }
+    };
+    entries.sort();
+
+    for xmlpath in entries {
+        if let Some(table) = rules.forFile(&xmlpath) {
+            let pathOout = outdir.join(xmlpath.file_name().unwrap()).with_extension(format.extension());
+            convertFileAs(&xmlpath, &pathOout, table, dirpath, format).unwrap_or_else(|err| {
+                println!("{}", err);
+                process::exit(7)
+            });
+        }
+    }
+}
+
+
+// Xml files are converted to the new format first and then written as a document in `format`.
+// JSON and YAML documents are read back and written in `format`.
+fn convertFileAs(xmlpath: &Path, pathOout: &Path, table: &TableRule, datadir: &Path, format: Format) -> Result<(), String> {
+    let isMercOpinions = table.file.eq_ignore_ascii_case(MercOpinions::FILE);
+
+    if let Some(Format::Json | Format::Yaml) = Format::fromPath(xmlpath) {
+        if isMercOpinions {
+            let data: MercOpinionsDocument = document::load(xmlpath)?;
+            let data = MercOpinions::fromDocument(&data).map_err(|e| format!("{}: {}", xmlpath.display(), e))?;
+            match format {
+                Format::Xml => data.saveMercOpinions(pathOout, table)?,
+                _ => document::save(&data.toDocument(), pathOout, format)?,
+            }
+        } else {
+            let data: Document = document::load(xmlpath)?;
+            match format {
+                Format::Xml => data.saveXml(pathOout, table)?,
+                _ => document::save(&data, pathOout, format)?,
+            }
+        }
+        return Ok(());
+    }
+
+    if format == Format::Xml {
+        return convertFile(xmlpath, pathOout, table, datadir);
+    }
+
+    let tempdir = env::temp_dir().join(format!("JA2-ConvertXMLData-{}", process::id()));
+    let temppath = tempdir.join(xmlpath.file_name().unwrap());
+    let result = convertFile(xmlpath, &temppath, table, datadir).and_then(|_| {
+        if isMercOpinions {
+            let data = MercOpinions::loadMercOpinions(&temppath, table)?;
+            document::save(&data.toDocument(), pathOout, format)?;
+        } else {
+            let data = Document::loadXml(&temppath, table)?;
+            document::save(&data, pathOout, format)?;
+        }
+        Ok(())
+    });
+    let _ = std::fs::remove_dir_all(&tempdir);
+    result
+}
+
+
+// Tables with their own loader get converted through it, the rest through the generic rule converter.
+// Tables referenced by the file are looked up in `datadir`.
+fn convertFile(xmlpath: &Path, pathOout: &Path, table: &TableRule, datadir: &Path) -> Result<(), String> {
+    let file = table.file.as_str();
+
+    if let Some(basepath) = localized::basePath(xmlpath).map(|p| datadir.join(p.file_name().unwrap())) {
+        if basepath.exists() {
+            for warning in localized::checkTranslation(xmlpath, &basepath, table)? {
+                println!("Warning: {}: {}", xmlpath.file_name().unwrap().to_string_lossy(), warning);
+            }
+        } else {
+            println!("Warning: {} not found, translated records were not checked", basepath.display());
+        }
+    }
+
+    if file.eq_ignore_ascii_case(MercOpinions::FILE) {
+        let data = MercOpinions::loadMercOpinions(xmlpath, table)?;
+        if table.comments {
+            // Nicknames of mercs without a record in the file come from MercProfiles.xml
+            let profilespath = datadir.join(MercProfiles::FILE);
+            let profiles = match profilespath.exists() {
+                true => Some(MercProfiles::loadMercProfiles(&profilespath)?),
+                false => None,
+            };
+            data.saveMercOpinionsCommented(pathOout, table, profiles.as_ref())?;
+        } else {
+            data.saveMercOpinions(pathOout, table)?;
+        }
+    } else if file.eq_ignore_ascii_case(LoadBearingEquipment::FILE) {
+        let data = LoadBearingEquipment::loadLoadBearingEquipment(xmlpath, table)?;
+        let pocketspath = datadir.join(Pockets::FILE);
+        if pocketspath.exists() {
+            let pockets = Pockets::loadPockets(&pocketspath)?;
+            for warning in data.checkPockets(&pockets) {
+                println!("Warning: {}", warning);
+            }
+        } else {
+            println!("Warning: {} not found in {}, pocket references were not checked", Pockets::FILE, datadir.display());
+        }
+        data.saveLoadBearingEquipment(pathOout, table)?;
+    } else if file.eq_ignore_ascii_case(NPCInventory::FILE) {
+        let data = NPCInventory::loadNPCInventory(xmlpath, table)?;
+        for warning in data.warnings() {
+            println!("Warning: {}: {}", xmlpath.file_name().unwrap().to_string_lossy(), warning);
+        }
+        let itemspath = datadir.join(Items::FILE);
+        if itemspath.exists() {
+            let items = Items::loadItems(&itemspath)?;
+            for warning in data.checkItems(&items) {
+                println!("Warning: {}: {}", xmlpath.file_name().unwrap().to_string_lossy(), warning);
+            }
+        } else {
+            println!("Warning: {} not found in {}, item indices were not checked", Items::FILE, datadir.display());
+        }
+        data.saveNPCInventory(pathOout, table)?;
+    } else if file.eq_ignore_ascii_case(Weapons::FILE) {
+        let data = Weapons::loadWeapons(xmlpath, table)?;
+        for warning in data.warnings() {
+            println!("Warning: {}", warning);
+        }
+        data.saveWeapons(pathOout, table)?;
+    } else {
+        table.convert(xmlpath, pathOout)?;
+    }
+    Ok(())
+}
+
+
+enum Command {
+    Convert,
+    InjectOpinions,
+    ExtractOpinions,
+    ImportProf,
+    ExportProf,
+    ExtractStrings,
+    InjectStrings,
+    ExportCsv,
+    ImportCsv,
+    ExportDb,
+    ImportDb,
+    ExportGraph,
+    Analyze,
+    Lint,
+    CheckRefs,
+    Diff,
+    Merge,
+    MakePatch,
+    ApplyPatch,
+    Edit,
+    Query,
+    Schema,
+    Validate,
+    Watch,
+}
+impl Command {
+    fn usage(&self) -> &'static str {
+        match self {
+            Command::Convert => "Provide path to JA2 1.13 xml file or TableData folder to be converted [--format xml|json|yaml] [--comments] [--symbolic] [--out folder]",
+            Command::InjectOpinions => "Usage: inject-opinions <MercProfiles.xml> <MercOpinions.xml> [--out file]",
+            Command::ExtractOpinions => "Usage: extract-opinions <MercProfiles.xml> [--out file]",
+            Command::ImportProf => "Usage: import-prof <Prof.dat> [--out file]",
+            Command::ExportProf => "Usage: export-prof <Prof.dat> <MercOpinions.xml> [--out file]",
+            Command::ExtractStrings => "Usage: extract-strings <table.xml> [--format po|xliff] [--out file]",
+            Command::InjectStrings => "Usage: inject-strings <strings.po|strings.xlf> <table.xml> [--out file]",
+            Command::ExportCsv => "Usage: export-csv <table.xml> [--out file]",
+            Command::ImportCsv => "Usage: import-csv <table.csv> [--out file]",
+            Command::ExportDb => "Usage: export-db <TableData folder or table.xml> [--out file]",
+            Command::ImportDb => "Usage: import-db <TableData.sqlite> [--out folder]",
+            Command::Analyze => "Usage: analyze <MercOpinions.xml> [--format text|json] [--out file]",
+            Command::Lint => "Usage: lint <MercOpinions.xml> [--config lint.toml] [--profiles MercProfiles.xml] [--format text|json]",
+            Command::CheckRefs => "Usage: check-refs <TableData folder>",
+            Command::Diff => "Usage: diff <old.xml> <new.xml>",
+            Command::MakePatch => "Usage: make-patch <base.xml> <mod.xml> [--format xml|json|yaml] [--out file]",
+            Command::ApplyPatch => "Usage: apply-patch <base.xml> <patch.xml|patch.json|patch.yaml> [--out file]",
+            Command::Edit => "Usage: edit <MercOpinions.xml> (--expr \"add aim -> Mike -10\" | --script edits.txt) [--out file]",
+            Command::Query => "Usage: query <MercOpinions.xml> (--merc nickname|uiIndex | --pair merc,merc)",
+            Command::Schema => "Usage: schema <table.xml or TableData folder> [--format xml|json] [--out file or folder]",
+            Command::Validate => "Usage: validate <table.xml or TableData folder>",
+            Command::Watch => "Usage: watch <TableData folder> [--format xml|json|yaml] [--out folder]",
+            Command::Merge => "Usage: merge <base.xml> <ours.xml> <theirs.xml> [--prefer ours|theirs] [--out file]",
+            Command::ExportGraph => "Usage: export-graph <MercOpinions.xml> [--min modifier] [--aim] [--merc nickname|uiIndex] [--out file]",
+        }
+    }
+
+    fn pathCount(&self) -> usize {
+        match self {
+            Command::InjectOpinions | Command::ExportProf | Command::InjectStrings | Command::Diff => 2,
+            Command::MakePatch | Command::ApplyPatch => 2,
+            Command::Merge => 3,
+            _ => 1,
+        }
+    }
 }
 
 
 struct Config {
-    xmlfilepath: String,
+    command: Command,
+    paths: Vec<String>,
+    rulesfilepath: Option<String>,
+    outpath: Option<String>,
+    format: Option<String>,
+    minModifier: Option<i32>,
+    aimOnly: bool,
+    merc: Option<String>,
+    configpath: Option<String>,
+    profilespath: Option<String>,
+    prefer: Option<String>,
+    expression: Option<String>,
+    scriptpath: Option<String>,
+    pair: Option<(String, String)>,
+    comments: bool,
+    symbolic: bool,
 }
 impl Config {
     fn new(args: &[String]) -> Result<Config, String> {
         if args.len() < 2 {
-            let errString = String::from("Not enough arguments!\nProvide path to JA2 1.13 xml file to be converted");
+            let errString = String::from("Not enough arguments!\nProvide path to JA2 1.13 xml file or TableData folder to be converted");
             return Err(errString);
         }
 
-        let xmlfilepath = args[1].clone();
-
-        Ok(Config {xmlfilepath})
-    }
-}
-
-
-struct MercOpinions
-{
-    index: Vec<u8>,
-    nicknames: Vec<String>,
-    opinions: Vec<Vec<i32>>
-}
-impl MercOpinions 
-{
-    fn new() -> MercOpinions
-    {
-        let index = Vec::new();
-        let nicknames = Vec::new();
-        let opinions = Vec::new();
-
-        return MercOpinions{index, nicknames, opinions};
-    }
-
-    fn loadMercOpinions(filepath: &PathBuf) -> MercOpinions
-    {
-        let mut mercOpinions = MercOpinions::new();
-
-        let reader = Reader::from_file(filepath);
-        match reader
-        {
-            Ok(mut reader) =>
-            {
-                reader.trim_text(true);
-                let mut buf = Vec::new();
-                loop 
-                {
-                    match reader.read_event_into(&mut buf) 
-                    {
-                        Err(element) => panic!("Error at position {}: {:?}", reader.buffer_position(), element),
-                        Ok(Event::Eof) => break,
-
-                        Ok(Event::Start(ref element)) => 
-                        {
-                            match element.name().as_ref() 
-                            {			
-                                b"OPINION" =>
-                                {
-                                    mercOpinions.readItem(&mut reader, &mut buf);
-                                }
-                                _ => {}
-                            }
-                        }
-                        _ => ()
+        // Without a command the argument is the file to convert
+        let (command, first) = match args[1].as_str() {
+            "inject-opinions" => (Command::InjectOpinions, 2),
+            "extract-opinions" => (Command::ExtractOpinions, 2),
+            "import-prof" => (Command::ImportProf, 2),
+            "export-prof" => (Command::ExportProf, 2),
+            "extract-strings" => (Command::ExtractStrings, 2),
+            "inject-strings" => (Command::InjectStrings, 2),
+            "export-csv" => (Command::ExportCsv, 2),
+            "import-csv" => (Command::ImportCsv, 2),
+            "export-db" => (Command::ExportDb, 2),
+            "import-db" => (Command::ImportDb, 2),
+            "export-graph" => (Command::ExportGraph, 2),
+            "analyze" => (Command::Analyze, 2),
+            "lint" => (Command::Lint, 2),
+            "check-refs" => (Command::CheckRefs, 2),
+            "diff" => (Command::Diff, 2),
+            "merge" => (Command::Merge, 2),
+            "make-patch" => (Command::MakePatch, 2),
+            "apply-patch" => (Command::ApplyPatch, 2),
+            "edit" => (Command::Edit, 2),
+            "query" => (Command::Query, 2),
+            "schema" => (Command::Schema, 2),
+            "validate" => (Command::Validate, 2),
+            "watch" => (Command::Watch, 2),
+            _ => (Command::Convert, 1),
+        };
+
+        let mut paths = Vec::new();
+        let mut rulesfilepath = None;
+        let mut outpath = None;
+        let mut format = None;
+        let mut minModifier = None;
+        let mut aimOnly = false;
+        let mut merc = None;
+        let mut configpath = None;
+        let mut profilespath = None;
+        let mut prefer = None;
+        let mut expression = None;
+        let mut scriptpath = None;
+        let mut pair = None;
+        let mut comments = false;
+        let mut symbolic = false;
+        let mut i = first;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--rules" => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(path) => rulesfilepath = Some(path.clone()),
+                        None => return Err(String::from("--rules needs a path to a rules file")),
                     }
-                    buf.clear();
                 }
-            }
-            Err(e) =>
-            {
-                println!("Error {}", e);
-                println!("Could not open file {}", filepath.display());
-            }
-        }
-        return mercOpinions;
-    }
-
-
-    fn saveMercOpinions(&self, filepath: &PathBuf)
-    {
-        let mut buffer = Vec::new();
-        // Write xml header before the xml data
-        // write!(buffer, "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n").unwrap();
-
-		write!(buffer, "<MERCOPINIONS>\n").unwrap();
-
-        for i in &self.index
-        {
-	    	write!(buffer, "\t<OPINION>\n").unwrap();
-
-            let value = i.clone();
-            write_tag_i!(buffer, value, "uiIndex", true);
-            
-            let value = &self.nicknames[*i as usize];
-            write_tag_s!(buffer, value, "zNickname", true);
-        
-            for j in 0..self.opinions[*i as usize].len()
-            {
-                let value = self.opinions[*i as usize][j];
-
-                let mut empty = false;
-                if value == 0 {empty = true;}
-        
-                if empty == false
-                {
-                    match write!(buffer, "\t\t<AnOpinion id = \"{}\" modifier = \"{}\"/>\n", j, value)
-                    {
-                        Ok(_) => {}
-                        Err(e) => {panic!("Error writing value {} for xml tag {}\n {:?}", value, "AnOpinion", e)}
+                "--out" => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(path) => outpath = Some(path.clone()),
+                        None => return Err(String::from("--out needs an output path")),
+                    }
+                }
+                "--format" => {
+                    i += 1;
+                    match args.get(i).map(|f| f.as_str()) {
+                        Some(f @ ("po" | "xliff" | "xml" | "json" | "yaml" | "text")) => format = Some(f.to_string()),
+                        _ => return Err(String::from("--format needs to be xml, json or yaml, po or xliff for extract-strings, or text for analyze")),
+                    }
+                }
+                "--min" => {
+                    i += 1;
+                    match args.get(i).and_then(|m| m.parse::<i32>().ok()) {
+                        Some(m) => minModifier = Some(m.abs()),
+                        None => return Err(String::from("--min needs a modifier")),
+                    }
+                }
+                "--aim" => aimOnly = true,
+                "--comments" => comments = true,
+                "--symbolic" => symbolic = true,
+                "--expr" => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(e) => expression = Some(e.clone()),
+                        None => return Err(String::from("--expr needs an edit, eg. \"add aim -> Mike -10\"")),
+                    }
+                }
+                "--script" => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(path) => scriptpath = Some(path.clone()),
+                        None => return Err(String::from("--script needs a path to an edit script")),
+                    }
+                }
+                "--prefer" => {
+                    i += 1;
+                    match args.get(i).map(|p| p.as_str()) {
+                        Some(p @ ("ours" | "theirs")) => prefer = Some(p.to_string()),
+                        _ => return Err(String::from("--prefer needs to be ours or theirs")),
+                    }
+                }
+                "--config" => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(path) => configpath = Some(path.clone()),
+                        None => return Err(String::from("--config needs a path to a config file")),
+                    }
+                }
+                "--profiles" => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(path) => profilespath = Some(path.clone()),
+                        None => return Err(String::from("--profiles needs a path to MercProfiles.xml")),
                     }
                 }
-        
+                "--merc" => {
+                    i += 1;
+                    match args.get(i) {
+                        Some(m) => merc = Some(m.clone()),
+                        None => return Err(String::from("--merc needs a nickname or uiIndex")),
+                    }
+                }
+                "--pair" => {
+                    i += 1;
+                    match args.get(i).and_then(|p| p.split_once(',')) {
+                        Some((a, b)) => pair = Some((a.to_string(), b.to_string())),
+                        None => return Err(String::from("--pair needs two mercs, eg. Ivan,Igor")),
+                    }
+                }
+                other if other.starts_with("--") => return Err(format!("Unknown argument {}", other)),
+                path => paths.push(path.to_string()),
             }
-
-            write!(buffer, "\t</OPINION>\n").unwrap();
+            i += 1;
         }
 
+        if paths.len() != command.pathCount() {
+            return Err(format!("Wrong number of arguments!\n{}", command.usage()));
+        }
 
-		write!(buffer, "</MERCOPINIONS>\n").unwrap();
-
-        println!("{}", &filepath.to_str().unwrap());
-        std::fs::create_dir_all(filepath.parent().unwrap());
-        let mut file = File::create(filepath).unwrap();
-        file.write_all(&buffer);
-    }
-
-    pub fn readItem(&mut self, reader: &mut Reader<BufReader<std::fs::File>>, buf: &mut Vec<u8>)
-	{
-		loop 
-		{
-			match reader.read_event_into(buf) 
-			{
-				    Ok(Event::Start(e)) => 
-				    {
-					        let name = str::from_utf8(e.name().as_ref()).unwrap().to_string();
-					        match e.name().as_ref()
-					        {
-			            		b"uiIndex" => { self.index.push(parseu8(reader, buf, &name)); }
-						        b"zNickname" => { self.nicknames.push(parseString(reader, buf, b"szWeaponName")); }
-			            		b"Opinion0" => { self.opinions.push(vec![parsei32(reader, buf, &name)]); }
-			            		b"Opinion1" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion2" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion3" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion4" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion5" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion6" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion7" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion8" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion9" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion10" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion11" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion12" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion13" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion14" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion15" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion16" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion17" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion18" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion19" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion20" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion21" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion22" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion23" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion24" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion25" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion26" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion27" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion28" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion29" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion30" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion31" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion32" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion33" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion34" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion35" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion36" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion37" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion38" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion39" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion40" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion41" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion42" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion43" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion44" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion45" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion46" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion47" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion48" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion49" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion50" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion51" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion52" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion53" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion54" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion55" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion56" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion57" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion58" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion59" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion60" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion61" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion62" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion63" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion64" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion65" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion66" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion67" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion68" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion69" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion70" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion71" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion72" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion73" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion74" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion75" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion76" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion77" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion78" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion79" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion80" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion81" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion82" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion83" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion84" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion85" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion86" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion87" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion88" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion89" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion90" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion91" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion92" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion93" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion94" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion95" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion96" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion97" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion98" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion99" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion100" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion101" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion102" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion103" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion104" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion105" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion106" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion107" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion108" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion109" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion110" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion111" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion112" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion113" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion114" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion115" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion116" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion117" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion118" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion119" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion120" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion121" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion122" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion123" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion124" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion125" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion126" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion127" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion128" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion129" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion130" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion131" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion132" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion133" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion134" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion135" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion136" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion137" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion138" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion139" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion140" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion141" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion142" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion143" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion144" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion145" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion146" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion147" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion148" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion149" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion150" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion151" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion152" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion153" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion154" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion155" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion156" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion157" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion158" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion159" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion160" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion161" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion162" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion163" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion164" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion165" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion166" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion167" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion168" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion169" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion170" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion171" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion172" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion173" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion174" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion175" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion176" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion177" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion178" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion179" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion180" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion181" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion182" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion183" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion184" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion185" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion186" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion187" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion188" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion189" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion190" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion191" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion192" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion193" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion194" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion195" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion196" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion197" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion198" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion199" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion200" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion201" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion202" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion203" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion204" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion205" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion206" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion207" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion208" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion209" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion210" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion211" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion212" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion213" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion214" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion215" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion216" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion217" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion218" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion219" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion220" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion221" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion222" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion223" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion224" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion225" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion226" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion227" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion228" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion229" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion230" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion231" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion232" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion233" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion234" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion235" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion236" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion237" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion238" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion239" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion240" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion241" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion242" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion243" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion244" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion245" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion246" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion247" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion248" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion249" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion250" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion251" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion252" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion253" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-			            		b"Opinion254" => { self.opinions.last_mut().unwrap().push(parsei32(reader, buf, &name)); }
-								_ => {}
-						        }
-				    }
-
-				    Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-				    Ok(Event::End(ref element)) => 
-				    {
-					        match element.name().as_ref()
-					        {
-						            b"OPINION" => break,
-						            _ => ()
-					        }
-				    }
-				    _ => (),
-			}
-			buf.clear();
-		}	
-	}
-}
-
-
-//-----------------------------------------------------------------------------
-// Functions
-//-----------------------------------------------------------------------------
-fn parseString(reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, tag: &[u8]) -> String
-{
-	loop {
-		match reader.read_event_into(buf) 
-		{
-			Ok(Event::Text(e)) => {
-				let value = e.unescape().unwrap().into_owned();
-				return value;
-			}
-			Ok(Event::End(ref element)) => 
-			{
-					match element.name().as_ref()
-					{
-							tag => break,
-							_ => ()
-					}
-			}
-			Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-			_ => {}
-		}
-	}
-
-	return "".to_string();
-}
-
-fn parsebool(reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, name: &str) -> bool
-{
-	loop {
-		match reader.read_event_into(buf) 
-		{
-			Ok(Event::Text(e)) => {
-				let value = e.unescape().unwrap().into_owned().parse::<u32>();
-				match value
-				{
-					Ok(value) => {return value != 0;}
-					_ => {println!("Error parsing value for tag {}", name); return false;}
-				}
-			}
-			Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-			_ => {}
-		}
-	}
-}
-
-macro_rules! parsers {
-	($($name:ident, $type:ty),*) => {
-		
-		$(fn $name(reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, name: &str) -> $type
-		{
-			loop {
-				match reader.read_event_into(buf) 
-				{
-					Ok(Event::Text(e)) => {
-						let value = e.unescape().unwrap().into_owned().parse::<$type>();
-						match value
-						{
-							Ok(value) => {return value;}
-							_ => {println!("Error parsing value for tag {} at position {}", name, reader.buffer_position()); return Default::default();}
-						}
-					}
-					Err(e) => panic!("Error at position {}: {:?}", reader.buffer_position(), e),
-					_ => {}
-				}
-			}
-		})*
-	};
-}
-parsers!(parseu8, u8, parsei8, i8, parseu16, u16, parsei16, i16, parseu32, u32, parsei32, i32, parseu64, u64, parsei64, i64, parsef32, f32);
-
+        Ok(Config {command, paths, rulesfilepath, outpath, format, minModifier, aimOnly, merc, configpath, profilespath, prefer, expression, scriptpath, pair, comments, symbolic})
+    }
+}
@@ -0,0 +1,429 @@
+use std::path::Path;
+use std::io::{self, BufReader, Write};
+use std::fs::File;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+use crate::rules::{Rule, TableRule};
+use crate::profiles::MercProfiles;
+use crate::{createOutput, parseString, parseu8, parsei32};
+
+
+pub struct MercOpinions
+{
+    pub index: Vec<u8>,
+    pub nicknames: Vec<String>,
+    pub opinions: Vec<Vec<i32>>
+}
+impl MercOpinions
+{
+    pub const FILE: &'static str = "MercOpinions.xml";
+
+    pub fn new() -> MercOpinions
+    {
+        let index = Vec::new();
+        let nicknames = Vec::new();
+        let opinions = Vec::new();
+
+        MercOpinions{index, nicknames, opinions}
+    }
+
+    /// Loads legacy and new format files. Opinions written as `<AnOpinion target = "Fox" .../>`
+    /// are resolved by the nicknames in the file once every record is read.
+    pub fn loadMercOpinions(filepath: &Path, table: &TableRule) -> Result<MercOpinions, String>
+    {
+        let mut mercOpinions = MercOpinions::new();
+        let mut targets = Vec::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(element) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), element)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) if element.name().as_ref() == table.record.as_bytes() =>
+                {
+                    mercOpinions.readItem(&mut reader, &mut buf, table, &mut targets)
+                        .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+
+        let mut errors = Vec::new();
+        for (k, target, value) in targets
+        {
+            match mercOpinions.resolveTarget(&target)
+            {
+                Ok(n) => setOpinion(&mut mercOpinions.opinions[k], n as usize, value),
+                Err(e) => errors.push(format!("{}: {} ({}): {}", filepath.display(), mercOpinions.name(mercOpinions.index[k]), mercOpinions.index[k], e))
+            }
+        }
+        if !errors.is_empty()
+        {
+            return Err(errors.join("\n"));
+        }
+        Ok(mercOpinions)
+    }
+
+
+    /// Writes the new format. Opinions get the target's nickname as a comment if the table's
+    /// comments option is on.
+    pub fn saveMercOpinions(&self, filepath: &Path, table: &TableRule) -> Result<(), String>
+    {
+        self.saveXml(filepath, table, table.comments, None)
+    }
+
+    /// Writes the new format with the target's nickname as a comment after each opinion. Nicknames
+    /// come from the file itself, then from `profiles` for targets without one.
+    pub fn saveMercOpinionsCommented(&self, filepath: &Path, table: &TableRule, profiles: Option<&MercProfiles>) -> Result<(), String>
+    {
+        self.saveXml(filepath, table, true, profiles)
+    }
+
+    fn saveXml(&self, filepath: &Path, table: &TableRule, comments: bool, profiles: Option<&MercProfiles>) -> Result<(), String>
+    {
+        let rule = table.rules.first()
+            .ok_or_else(|| format!("Conversion rules for {} have no opinion rule", table.file))?;
+
+        // Records go straight to the file as they are written
+        let mut writer = createOutput(filepath)?;
+        self.writeXml(&mut writer, table, rule, comments, profiles)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    fn writeXml<W: Write>(&self, writer: &mut W, table: &TableRule, rule: &Rule, comments: bool, profiles: Option<&MercProfiles>) -> io::Result<()>
+    {
+        // Write xml header before the xml data
+        // write!(writer, "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n")?;
+
+		writeln!(writer, "<{}>", table.root)?;
+
+        for (k, i) in self.index.iter().enumerate()
+        {
+	    	writeln!(writer, "\t<{}>", table.record)?;
+
+            let value = *i;
+            write_tag_i!(writer, value, "uiIndex", true)?;
+
+            let value = &self.nicknames[k];
+            write_tag_s!(writer, value, "zNickname", true)?;
+
+            for (j, value) in self.opinions[k].iter().enumerate()
+            {
+                let value = value.to_string();
+                if !rule.skips(&value)
+                {
+                    let comment = match self.commentName(j, profiles)
+                    {
+                        Some(name) if comments => format!(" <!-- {} -->", name.replace("--", "- -")),
+                        _ => String::new()
+                    };
+                    // Targets are only written by nickname if it names no other merc
+                    let element = match self.nickname(j)
+                    {
+                        Some(name) if table.symbolic && self.resolveTarget(&name) == Ok(j as u8) => rule.renderSymbolic(&name, &value),
+                        _ => rule.render(j as u32, &value)
+                    };
+                    writeln!(writer, "\t\t<{}/>{}", element, comment)?;
+                }
+            }
+
+            writeln!(writer, "\t</{}>", table.record)?;
+        }
+
+
+		writeln!(writer, "</{}>", table.root)
+    }
+
+    /// JSON and YAML form, with the non-zero opinions of each merc
+    pub fn toDocument(&self) -> MercOpinionsDocument
+    {
+        let records = self.index.iter().enumerate()
+            .map(|(k, i)| OpinionRecord{
+                uiIndex: *i,
+                zNickname: self.nicknames[k].clone(),
+                opinions: self.opinions[k].iter().enumerate()
+                    .filter(|(_, modifier)| **modifier != 0)
+                    .map(|(id, modifier)| AnOpinion{id, modifier: *modifier})
+                    .collect()
+            })
+            .collect();
+
+        MercOpinionsDocument{table: MercOpinions::FILE.to_string(), records}
+    }
+
+    pub fn fromDocument(document: &MercOpinionsDocument) -> Result<MercOpinions, String>
+    {
+        let mut mercOpinions = MercOpinions::new();
+        for record in &document.records
+        {
+            let mut row = Vec::new();
+            for opinion in &record.opinions
+            {
+                let id = checkOpinionId(opinion.id).map_err(|e| format!("uiIndex {}: {}", record.uiIndex, e))?;
+                setOpinion(&mut row, id, opinion.modifier);
+            }
+            mercOpinions.index.push(record.uiIndex);
+            mercOpinions.nicknames.push(record.zNickname.clone());
+            mercOpinions.opinions.push(row);
+        }
+        Ok(mercOpinions)
+    }
+
+    /// uiIndex of a merc given by uiIndex or nickname
+    pub fn findMerc(&self, merc: &str) -> Result<u8, String>
+    {
+        if let Ok(index) = merc.trim().parse::<u8>()
+        {
+            return Ok(index);
+        }
+        match self.nicknames.iter().position(|n| n.eq_ignore_ascii_case(merc.trim()))
+        {
+            Some(k) => Ok(self.index[k]),
+            None => Err(format!("No merc with the nickname {}", merc))
+        }
+    }
+
+    /// Nickname of the merc with `uiIndex`, or the uiIndex for mercs without a record or nickname
+    pub fn name(&self, uiIndex: u8) -> String
+    {
+        match self.position(uiIndex)
+        {
+            Some(k) if !self.nicknames[k].is_empty() => self.nicknames[k].clone(),
+            _ => uiIndex.to_string()
+        }
+    }
+
+    /// Nickname of target `j` for comments, from the file or else from `profiles`
+    fn commentName(&self, j: usize, profiles: Option<&MercProfiles>) -> Option<String>
+    {
+        let j = u8::try_from(j).ok()?;
+        let nickname = |index: &[u8], nicknames: &[String]| index.iter().position(|i| *i == j)
+            .map(|k| nicknames[k].trim().to_string())
+            .filter(|n| !n.is_empty());
+        nickname(&self.index, &self.nicknames)
+            .or_else(|| profiles.and_then(|p| nickname(&p.index, &p.nicknames)))
+    }
+
+    fn nickname(&self, j: usize) -> Option<String>
+    {
+        self.commentName(j, None)
+    }
+
+    /// uiIndex of the merc a symbolic `target` names. Exact nicknames are preferred over ones
+    /// differing in case, a number is taken as the uiIndex.
+    pub fn resolveTarget(&self, target: &str) -> Result<u8, String>
+    {
+        let target = target.trim();
+        if let Ok(index) = target.parse::<u8>()
+        {
+            return Ok(index);
+        }
+
+        let find = |same: &dyn Fn(&str) -> bool| -> Vec<u8>
+        {
+            self.index.iter().zip(&self.nicknames).filter(|(_, n)| same(n.trim())).map(|(i, _)| *i).collect()
+        };
+        let mut found = find(&|n| n == target);
+        if found.is_empty()
+        {
+            found = find(&|n| n.eq_ignore_ascii_case(target));
+        }
+
+        match found.as_slice()
+        {
+            [index] => Ok(*index),
+            [] =>
+            {
+                let suggestions = suggestions(target, &self.nicknames);
+                match suggestions.is_empty()
+                {
+                    true => Err(format!("Unknown target {}", target)),
+                    false => Err(format!("Unknown target {}, did you mean {}?", target, suggestions.join(" or ")))
+                }
+            }
+            indices =>
+            {
+                let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+                Err(format!("Target {} is ambiguous, it is the nickname of uiIndex {}; use id = \"n\" instead", target, indices.join(", ")))
+            }
+        }
+    }
+
+    /// Position of the record with `uiIndex`
+    pub fn position(&self, uiIndex: u8) -> Option<usize>
+    {
+        self.index.iter().position(|i| *i == uiIndex)
+    }
+
+    /// Symbolic opinions are added to `targets` as record position, target and modifier
+    pub fn readItem(&mut self, reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, table: &TableRule, targets: &mut Vec<(usize, String, i32)>) -> Result<(), String>
+	{
+		self.index.push(0);
+		self.nicknames.push(String::new());
+		self.opinions.push(Vec::new());
+		loop
+		{
+			match reader.read_event_into(buf)
+			{
+				    Ok(Event::Start(e)) =>
+				    {
+					        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+					        match e.name().as_ref()
+					        {
+			            		b"uiIndex" => { *self.index.last_mut().unwrap() = parseu8(reader, buf, &name)?; }
+						        b"zNickname" => { *self.nicknames.last_mut().unwrap() = parseString(reader, buf, b"zNickname")?; }
+								_ =>
+								{
+									// Opinion{n} tags, numbered by the target merc
+									if let Some((_, n)) = table.rule(&name)
+									{
+										let n = checkOpinionId(n as usize).map_err(|e| format!("{} at position {}", e, reader.buffer_position()))?;
+										let value = parsei32(reader, buf, &name)?;
+										setOpinion(self.opinions.last_mut().unwrap(), n, value);
+									}
+								}
+						        }
+				    }
+
+				    // Already converted files
+				    Ok(Event::Empty(e)) =>
+				    {
+					        for rule in &table.rules
+					        {
+						        if let Some((n, value)) = rule.matchTarget(&e)
+						        {
+							        let n = checkOpinionId(n as usize).map_err(|e| format!("{} at position {}", e, reader.buffer_position()))?;
+							        setOpinion(self.opinions.last_mut().unwrap(), n, parseModifier(&value, reader.buffer_position())?);
+						        }
+						        else if let Some((target, value)) = rule.matchSymbolic(&e)
+						        {
+							        targets.push((self.index.len() - 1, target, parseModifier(&value, reader.buffer_position())?));
+						        }
+					        }
+				    }
+
+				    Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+				    Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", table.record)),
+				    Ok(Event::End(ref element)) if element.name().as_ref() == table.record.as_bytes() => break,
+				    _ => (),
+			}
+			buf.clear();
+		}
+		Ok(())
+	}
+}
+
+
+#[derive(Serialize, Deserialize)]
+pub struct MercOpinionsDocument
+{
+    pub table: String,
+    pub records: Vec<OpinionRecord>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OpinionRecord
+{
+    pub uiIndex: u8,
+    #[serde(default)]
+    pub zNickname: String,
+    #[serde(default)]
+    pub opinions: Vec<AnOpinion>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnOpinion
+{
+    pub id: usize,
+    pub modifier: i32
+}
+
+
+/// Reads the target and modifier of a new format `<AnOpinion id = "n" modifier = "v"/>` element.
+pub fn readAnOpinion(element: &BytesStart) -> Option<(usize, i32)>
+{
+    if element.name().as_ref() != b"AnOpinion" { return None; }
+
+    let mut id = None;
+    let mut modifier = None;
+    for attribute in element.attributes().flatten()
+    {
+        let value = attribute.unescape_value().ok()?;
+        match attribute.key.as_ref()
+        {
+            b"id" => id = value.trim().parse::<usize>().ok(),
+            b"modifier" => modifier = value.trim().parse::<i32>().ok(),
+            _ => {}
+        }
+    }
+    Some((id?, modifier?))
+}
+
+fn parseModifier(value: &str, position: usize) -> Result<i32, String>
+{
+    value.trim().parse().map_err(|_| format!("Invalid modifier {} at position {}", value, position))
+}
+
+/// Nicknames close to `name`: starting the same way or at most two letters off
+fn suggestions(name: &str, nicknames: &[String]) -> Vec<String>
+{
+    let name = name.to_lowercase();
+    let mut close: Vec<String> = Vec::new();
+    for nickname in nicknames.iter().map(|n| n.trim()).filter(|n| !n.is_empty())
+    {
+        let lower = nickname.to_lowercase();
+        let near = (name.len() >= 3 && lower.starts_with(&name)) || distance(&name, &lower) <= 2.min(name.len() / 2);
+        if near && !close.iter().any(|c| c == nickname)
+        {
+            close.push(nickname.to_string());
+        }
+    }
+    close
+}
+
+/// Levenshtein distance
+fn distance(a: &str, b: &str) -> usize
+{
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate()
+    {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate()
+        {
+            let current = row[j + 1];
+            row[j + 1] = (previous + usize::from(ca != *cb)).min(row[j] + 1).min(current + 1);
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+/// Opinions are kept in arrays of 255 mercs in the game, higher targets can't be stored
+pub const MAX_OPINION_ID: usize = 254;
+
+pub fn checkOpinionId(n: usize) -> Result<usize, String>
+{
+    if n > MAX_OPINION_ID
+    {
+        return Err(format!("Opinion target {} is above the highest merc id {}", n, MAX_OPINION_ID));
+    }
+    Ok(n)
+}
+
+pub fn setOpinion(row: &mut Vec<i32>, n: usize, value: i32)
+{
+    if row.len() <= n { row.resize(n + 1, 0); }
+    row[n] = value;
+}
@@ -0,0 +1,131 @@
+use crate::mercopinions::{MercOpinions, setOpinion};
+use crate::diff::modifier;
+
+
+//-----------------------------------------------------------------------------
+// Three-way merge of MercOpinions
+//-----------------------------------------------------------------------------
+// Records are matched by uiIndex and merged cell by cell: the nickname and every opinion.
+// A cell changed on one side only takes that side's value, a cell changed the same way on
+// both sides takes it too. Cells changed differently on both sides are conflicts, which take
+// the preferred side's value and are reported. Records added on one side are taken as they
+// are; a record removed on one side and changed on the other is a conflict as well.
+
+pub struct Conflict
+{
+    pub merc: u8,
+    pub message: String
+}
+
+pub struct Merge
+{
+    pub merged: MercOpinions,
+    pub conflicts: Vec<Conflict>
+}
+impl Merge
+{
+    pub fn mergeMercOpinions(base: &MercOpinions, ours: &MercOpinions, theirs: &MercOpinions, preferTheirs: bool) -> Merge
+    {
+        let mut merge = Merge{merged: MercOpinions::new(), conflicts: Vec::new()};
+
+        // Records in the order of ours, then the ones only theirs has
+        let mut mercs: Vec<u8> = ours.index.clone();
+        mercs.extend(theirs.index.iter().filter(|i| ours.position(**i).is_none()));
+        mercs.extend(base.index.iter().filter(|i| !mercs.contains(i)).collect::<Vec<_>>());
+
+        for merc in mercs
+        {
+            let b = record(base, merc);
+            let o = record(ours, merc);
+            let t = record(theirs, merc);
+            let name = || ours.position(merc).map(|_| ours.name(merc)).unwrap_or_else(|| theirs.name(merc));
+
+            match (o, t)
+            {
+                (None, None) => {}
+                (Some(o), None) | (None, Some(o)) if b.is_none() => merge.push(merc, o.0, o.1.clone()),
+                (Some(kept), None) | (None, Some(kept)) =>
+                {
+                    let removedInOurs = ours.position(merc).is_none();
+                    if sameRecord(b.unwrap(), kept) { continue; }
+
+                    let (removed, changed) = if removedInOurs { ("ours", "theirs") } else { ("theirs", "ours") };
+                    merge.conflicts.push(Conflict{merc, message: format!("{} ({}): removed in {}, changed in {}", name(), merc, removed, changed)});
+                    if removedInOurs == preferTheirs
+                    {
+                        merge.push(merc, kept.0, kept.1.clone());
+                    }
+                }
+                (Some(o), Some(t)) =>
+                {
+                    let empty = Vec::new();
+                    let (baseNickname, baseRow) = b.unwrap_or(("", &empty));
+
+                    let nickname = match merge3(&baseNickname, &o.0, &t.0)
+                    {
+                        Some(nickname) => nickname,
+                        None =>
+                        {
+                            merge.conflicts.push(Conflict{merc, message: format!("Nickname of {}: base {}, ours {}, theirs {}",
+                                merc, baseNickname, o.0, t.0)});
+                            if preferTheirs { t.0 } else { o.0 }
+                        }
+                    };
+
+                    let mut row = Vec::new();
+                    let columns = baseRow.len().max(o.1.len()).max(t.1.len());
+                    for target in 0..columns
+                    {
+                        let (bv, ov, tv) = (opinion(baseRow, target), opinion(o.1, target), opinion(t.1, target));
+                        let value = match merge3(&bv, &ov, &tv)
+                        {
+                            Some(value) => value,
+                            None =>
+                            {
+                                let targetName = u8::try_from(target).map(|t| ours.name(t)).unwrap_or_else(|_| target.to_string());
+                                merge.conflicts.push(Conflict{merc, message: format!("{} -> {}: base {}, ours {}, theirs {}",
+                                    name(), targetName, modifier(bv), modifier(ov), modifier(tv))});
+                                if preferTheirs { tv } else { ov }
+                            }
+                        };
+                        if value != 0 { setOpinion(&mut row, target, value); }
+                    }
+                    merge.push(merc, nickname, row);
+                }
+            }
+        }
+
+        merge
+    }
+
+    fn push(&mut self, merc: u8, nickname: &str, row: Vec<i32>)
+    {
+        self.merged.index.push(merc);
+        self.merged.nicknames.push(nickname.to_string());
+        self.merged.opinions.push(row);
+    }
+}
+
+
+fn record(mercOpinions: &MercOpinions, merc: u8) -> Option<(&str, &Vec<i32>)>
+{
+    mercOpinions.position(merc).map(|k| (mercOpinions.nicknames[k].as_str(), &mercOpinions.opinions[k]))
+}
+
+fn opinion(row: &[i32], target: usize) -> i32
+{
+    row.get(target).copied().unwrap_or(0)
+}
+
+fn sameRecord(a: (&str, &Vec<i32>), b: (&str, &Vec<i32>)) -> bool
+{
+    a.0 == b.0 && (0..a.1.len().max(b.1.len())).all(|j| opinion(a.1, j) == opinion(b.1, j))
+}
+
+/// Merged value, or None for a conflict
+fn merge3<T: PartialEq + Copy>(base: &T, ours: &T, theirs: &T) -> Option<T>
+{
+    if ours == theirs || theirs == base { Some(*ours) }
+    else if ours == base { Some(*theirs) }
+    else { None }
+}
@@ -0,0 +1,298 @@
+use std::path::Path;
+use std::io::Write;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+use crate::mercopinions::{MercOpinions, AnOpinion, readAnOpinion, setOpinion, checkOpinionId};
+use crate::diff::OpinionDiff;
+use crate::document::{self, Format};
+use crate::createOutput;
+
+
+//-----------------------------------------------------------------------------
+// Override patches
+//-----------------------------------------------------------------------------
+// A patch holds only the records a mod changes compared to the base file:
+//
+//   <PATCH table = "MercOpinions.xml">
+//       <OPINION uiIndex = "3" action = "change">
+//           <zNickname>Fox</zNickname>                      only if it changed
+//           <AnOpinion id = "5" modifier = "0"/>            changed opinions, 0 removes one
+//       </OPINION>
+//       <OPINION uiIndex = "60" action = "add"> ... the whole record ... </OPINION>
+//       <OPINION uiIndex = "9" action = "remove"/>
+//   </PATCH>
+//
+// The same patch can be written as JSON or YAML, with the fields named like the attributes.
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action
+{
+    Add,
+    Change,
+    Remove
+}
+impl Action
+{
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Action::Add => "add",
+            Action::Change => "change",
+            Action::Remove => "remove"
+        }
+    }
+
+    fn fromName(name: &str) -> Option<Action>
+    {
+        match name
+        {
+            "add" => Some(Action::Add),
+            "change" => Some(Action::Change),
+            "remove" => Some(Action::Remove),
+            _ => None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PatchRecord
+{
+    pub uiIndex: u8,
+    pub action: Action,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zNickname: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub opinions: Vec<AnOpinion>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct OpinionPatch
+{
+    pub table: String,
+    pub records: Vec<PatchRecord>
+}
+impl OpinionPatch
+{
+    /// Patch that turns `base` into `modded`
+    pub fn makePatch(base: &MercOpinions, modded: &MercOpinions) -> OpinionPatch
+    {
+        let diff = OpinionDiff::diffMercOpinions(base, modded);
+        let mut records: Vec<PatchRecord> = Vec::new();
+
+        for merc in &modded.index
+        {
+            if diff.added.contains(merc)
+            {
+                let k = modded.position(*merc).unwrap();
+                let opinions = modded.opinions[k].iter().enumerate()
+                    .filter(|(_, modifier)| **modifier != 0)
+                    .map(|(id, modifier)| AnOpinion{id, modifier: *modifier})
+                    .collect();
+                records.push(PatchRecord{uiIndex: *merc, action: Action::Add, zNickname: Some(modded.nicknames[k].clone()), opinions});
+                continue;
+            }
+
+            let zNickname = diff.nicknames.iter().find(|(m, _, _)| m == merc).map(|(_, _, new)| new.clone());
+            let opinions: Vec<AnOpinion> = diff.opinions.iter().filter(|c| c.merc == *merc)
+                .map(|c| AnOpinion{id: c.target, modifier: c.new})
+                .collect();
+            if zNickname.is_some() || !opinions.is_empty()
+            {
+                records.push(PatchRecord{uiIndex: *merc, action: Action::Change, zNickname, opinions});
+            }
+        }
+        for merc in &diff.removed
+        {
+            records.push(PatchRecord{uiIndex: *merc, action: Action::Remove, zNickname: None, opinions: Vec::new()});
+        }
+
+        OpinionPatch{table: MercOpinions::FILE.to_string(), records}
+    }
+
+    /// The full table: `base` with the patch applied. Added records go before the first record with
+    /// a higher uiIndex, so sorted files stay sorted.
+    pub fn applyPatch(&self, base: &MercOpinions) -> Result<MercOpinions, String>
+    {
+        let mut result = MercOpinions::new();
+        result.index = base.index.clone();
+        result.nicknames = base.nicknames.clone();
+        result.opinions = base.opinions.clone();
+
+        for record in &self.records
+        {
+            let merc = record.uiIndex;
+            match (record.action, result.position(merc))
+            {
+                (Action::Remove, Some(k)) =>
+                {
+                    result.index.remove(k);
+                    result.nicknames.remove(k);
+                    result.opinions.remove(k);
+                }
+                (Action::Change, Some(k)) =>
+                {
+                    if let Some(nickname) = &record.zNickname
+                    {
+                        result.nicknames[k] = nickname.clone();
+                    }
+                    for opinion in &record.opinions
+                    {
+                        let id = checkOpinionId(opinion.id).map_err(|e| format!("Patch of uiIndex {}: {}", merc, e))?;
+                        setOpinion(&mut result.opinions[k], id, opinion.modifier);
+                    }
+                }
+                (Action::Add, None) =>
+                {
+                    let mut row = Vec::new();
+                    for opinion in &record.opinions
+                    {
+                        let id = checkOpinionId(opinion.id).map_err(|e| format!("Patch of uiIndex {}: {}", merc, e))?;
+                        setOpinion(&mut row, id, opinion.modifier);
+                    }
+                    let k = result.index.iter().position(|i| *i > merc).unwrap_or(result.index.len());
+                    result.index.insert(k, merc);
+                    result.nicknames.insert(k, record.zNickname.clone().unwrap_or_default());
+                    result.opinions.insert(k, row);
+                }
+                (Action::Add, Some(_)) => return Err(format!("Patch adds uiIndex {}, which the base file already has", merc)),
+                (_, None) => return Err(format!("Patch {}s uiIndex {}, which the base file doesn't have", record.action.name(), merc))
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Saves as xml, or JSON or YAML by the extension of `filepath`
+    pub fn save(&self, filepath: &Path) -> Result<(), String>
+    {
+        match Format::fromPath(filepath)
+        {
+            Some(format @ (Format::Json | Format::Yaml)) => document::save(self, filepath, format),
+            _ => self.saveXml(filepath)
+        }
+    }
+
+    pub fn load(filepath: &Path) -> Result<OpinionPatch, String>
+    {
+        match Format::fromPath(filepath)
+        {
+            Some(Format::Json | Format::Yaml) => document::load(filepath),
+            _ => OpinionPatch::loadXml(filepath)
+        }
+    }
+
+    fn saveXml(&self, filepath: &Path) -> Result<(), String>
+    {
+        let mut buffer = Vec::new();
+
+		writeln!(buffer, "<PATCH table = \"{}\">", self.table).unwrap();
+
+        for record in &self.records
+        {
+            if record.action == Action::Remove
+            {
+                writeln!(buffer, "\t<OPINION uiIndex = \"{}\" action = \"remove\"/>", record.uiIndex).unwrap();
+                continue;
+            }
+
+	    	writeln!(buffer, "\t<OPINION uiIndex = \"{}\" action = \"{}\">", record.uiIndex, record.action.name()).unwrap();
+            if let Some(value) = &record.zNickname
+            {
+                write_tag_s!(buffer, value, "zNickname", true).unwrap();
+            }
+            for opinion in &record.opinions
+            {
+                writeln!(buffer, "\t\t<AnOpinion id = \"{}\" modifier = \"{}\"/>", opinion.id, opinion.modifier).unwrap();
+            }
+            writeln!(buffer, "\t</OPINION>").unwrap();
+        }
+
+		writeln!(buffer, "</PATCH>").unwrap();
+
+        let mut file = createOutput(filepath)?;
+        file.write_all(&buffer)
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    fn loadXml(filepath: &Path) -> Result<OpinionPatch, String>
+    {
+        let mut patch = OpinionPatch{table: MercOpinions::FILE.to_string(), records: Vec::new()};
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut inNickname = false;
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(e) => return Err(format!("{}: error at position {}: {:?}", filepath.display(), reader.buffer_position(), e)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e)) if e.name().as_ref() == b"OPINION" =>
+                {
+                    let mut uiIndex = None;
+                    let mut action = None;
+                    for attribute in e.attributes().flatten()
+                    {
+                        let value = attribute.unescape_value().map_err(|e| format!("{}: {:?}", filepath.display(), e))?;
+                        match attribute.key.as_ref()
+                        {
+                            b"uiIndex" => uiIndex = value.trim().parse::<u8>().ok(),
+                            b"action" => action = Action::fromName(value.trim()),
+                            _ => {}
+                        }
+                    }
+                    match (uiIndex, action)
+                    {
+                        (Some(uiIndex), Some(action)) => patch.records.push(PatchRecord{uiIndex, action, zNickname: None, opinions: Vec::new()}),
+                        _ => return Err(format!("{}: OPINION needs a uiIndex and an action of add, change or remove", filepath.display()))
+                    }
+                }
+                Ok(Event::Start(ref e)) if e.name().as_ref() == b"PATCH" =>
+                {
+                    if let Ok(Some(table)) = e.try_get_attribute("table")
+                    {
+                        patch.table = table.unescape_value().map_err(|e| format!("{}: {:?}", filepath.display(), e))?.into_owned();
+                    }
+                }
+                Ok(Event::Start(ref e)) => inNickname = e.name().as_ref() == b"zNickname",
+                Ok(Event::Text(ref e)) if inNickname =>
+                {
+                    if let Some(record) = patch.records.last_mut()
+                    {
+                        record.zNickname = Some(e.unescape().map_err(|e| format!("{}: {:?}", filepath.display(), e))?.into_owned());
+                    }
+                }
+                Ok(Event::End(ref e)) if e.name().as_ref() == b"zNickname" =>
+                {
+                    // <zNickname></zNickname> sets an empty nickname
+                    if let Some(record) = patch.records.last_mut()
+                    {
+                        record.zNickname.get_or_insert_with(String::new);
+                    }
+                    inNickname = false;
+                }
+                Ok(Event::Empty(ref e)) =>
+                {
+                    if let (Some((id, modifier)), Some(record)) = (readAnOpinion(e), patch.records.last_mut())
+                    {
+                        let id = checkOpinionId(id).map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                        record.opinions.push(AnOpinion{id, modifier});
+                    }
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+
+        Ok(patch)
+    }
+}
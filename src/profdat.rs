@@ -0,0 +1,171 @@
+use std::path::Path;
+use std::io::Write;
+use std::fs::File;
+
+use crate::mercopinions::MercOpinions;
+
+
+// Layout of a MERCPROFILESTRUCT record in BinaryData/Prof.dat (Soldier Profile Type.h of the JA2 source),
+// as far as this tool reads it. zName comes first, the nickname is CHAR16 zNickname[10],
+// UTF-16LE and zero padded, the opinions are INT8 bMercOpinion[75] behind usOptionalGearCost.
+pub const RECORD_SIZE: usize = 716;
+pub const NICKNAME_OFFSET: usize = 60;
+pub const NICKNAME_LENGTH: usize = 10;
+pub const OPINIONS_OFFSET: usize = 574;
+pub const OPINION_COUNT: usize = 75;
+
+// Every record is read and written with JA2EncryptedFileRead/JA2EncryptedFileWrite (Encrypted File.cpp):
+// each byte is offset by the previous encrypted byte and the next entry of this table, starting over
+// for every record. The original source has the 45th entry as 057, an octal literal.
+const ROTATION: [u8; 49] = [
+    132, 235, 125, 99, 15, 220, 140, 89, 205, 132,
+    254, 144, 217, 78, 156, 58, 215, 76, 163, 187,
+    55, 49, 65, 48, 156, 140, 201, 68, 184, 13,
+    45, 69, 102, 185, 122, 225, 23, 250, 160, 220,
+    114, 240, 64, 175, 0o57, 233, 133, 72, 138
+];
+
+pub fn decrypt(record: &mut [u8])
+{
+    let mut last = 0u8;
+    for (i, byte) in record.iter_mut().enumerate()
+    {
+        let encrypted = *byte;
+        *byte = byte.wrapping_sub(last.wrapping_add(ROTATION[i % ROTATION.len()]));
+        last = encrypted;
+    }
+}
+
+pub fn encrypt(record: &mut [u8])
+{
+    let mut last = 0u8;
+    for (i, byte) in record.iter_mut().enumerate()
+    {
+        *byte = byte.wrapping_add(last.wrapping_add(ROTATION[i % ROTATION.len()]));
+        last = *byte;
+    }
+}
+
+
+// Records are kept as raw bytes so everything this tool doesn't understand is written back untouched
+pub struct ProfDat
+{
+    pub records: Vec<Vec<u8>>
+}
+impl ProfDat
+{
+    pub const FILE: &'static str = "Prof.dat";
+
+    pub fn loadProfDat(filepath: &Path) -> Result<ProfDat, String>
+    {
+        let data = std::fs::read(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        if data.is_empty() || data.len() % RECORD_SIZE != 0
+        {
+            return Err(format!("{} is {} bytes, which is not a whole number of {} byte profile records",
+                filepath.display(), data.len(), RECORD_SIZE));
+        }
+
+        let records = data.chunks(RECORD_SIZE)
+            .map(|r| { let mut record = r.to_vec(); decrypt(&mut record); record })
+            .collect();
+        Ok(ProfDat{records})
+    }
+
+    pub fn saveProfDat(&self, filepath: &Path)
+    {
+        println!("{}", &filepath.to_str().unwrap());
+        std::fs::create_dir_all(filepath.parent().unwrap()).unwrap();
+        let mut file = File::create(filepath).unwrap();
+        for record in &self.records
+        {
+            let mut record = record.clone();
+            encrypt(&mut record);
+            file.write_all(&record).unwrap();
+        }
+    }
+
+    pub fn nickname(&self, profile: usize) -> String
+    {
+        let bytes = &self.records[profile][NICKNAME_OFFSET..NICKNAME_OFFSET + NICKNAME_LENGTH * 2];
+        let chars: Vec<u16> = bytes.chunks(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|c| *c != 0)
+            .collect();
+        String::from_utf16_lossy(&chars)
+    }
+
+    pub fn setNickname(&mut self, profile: usize, nickname: &str)
+    {
+        // Bytes behind the terminating zero are left as they were when nothing changes
+        if self.nickname(profile) == nickname { return; }
+
+        let chars: Vec<u16> = nickname.encode_utf16().collect();
+        if chars.len() > NICKNAME_LENGTH - 1
+        {
+            println!("Warning: nickname '{}' is longer than {} characters and was cut", nickname, NICKNAME_LENGTH - 1);
+        }
+
+        let bytes = &mut self.records[profile][NICKNAME_OFFSET..NICKNAME_OFFSET + NICKNAME_LENGTH * 2];
+        bytes.fill(0);
+        for (i, c) in chars.iter().take(NICKNAME_LENGTH - 1).enumerate()
+        {
+            bytes[i * 2..i * 2 + 2].copy_from_slice(&c.to_le_bytes());
+        }
+    }
+
+    pub fn opinions(&self, profile: usize) -> Vec<i32>
+    {
+        self.records[profile][OPINIONS_OFFSET..OPINIONS_OFFSET + OPINION_COUNT].iter()
+            .map(|b| *b as i8 as i32)
+            .collect()
+    }
+
+    pub fn setOpinions(&mut self, profile: usize, opinions: &[i32])
+    {
+        let bytes = &mut self.records[profile][OPINIONS_OFFSET..OPINIONS_OFFSET + OPINION_COUNT];
+        bytes.fill(0);
+        for (j, value) in opinions.iter().enumerate().take(OPINION_COUNT)
+        {
+            let clamped = (*value).clamp(i8::MIN as i32, i8::MAX as i32);
+            if clamped != *value
+            {
+                println!("Warning: opinion {} of profile {} does not fit in a byte and was set to {}", value, profile, clamped);
+            }
+            bytes[j] = clamped as i8 as u8;
+        }
+        for (j, value) in opinions.iter().enumerate().skip(OPINION_COUNT).filter(|(_, value)| **value != 0)
+        {
+            println!("Warning: opinion {} of profile {} about {} is dropped, {} only holds {} opinions", value, profile, j, ProfDat::FILE, OPINION_COUNT);
+        }
+    }
+
+    /// Profile number is the record's position in the file
+    pub fn toMercOpinions(&self) -> MercOpinions
+    {
+        let mut mercOpinions = MercOpinions::new();
+        for profile in 0..self.records.len().min(u8::MAX as usize + 1)
+        {
+            mercOpinions.index.push(profile as u8);
+            mercOpinions.nicknames.push(self.nickname(profile));
+            mercOpinions.opinions.push(self.opinions(profile));
+        }
+        mercOpinions
+    }
+
+    /// Writes nicknames and opinions of `mercOpinions` into the records with the same uiIndex
+    pub fn applyMercOpinions(&mut self, mercOpinions: &MercOpinions)
+    {
+        for k in 0..mercOpinions.index.len()
+        {
+            let profile = mercOpinions.index[k] as usize;
+            if profile >= self.records.len()
+            {
+                println!("Warning: uiIndex {} has no record in {}", profile, ProfDat::FILE);
+                continue;
+            }
+            self.setNickname(profile, &mercOpinions.nicknames[k]);
+            self.setOpinions(profile, &mercOpinions.opinions[k]);
+        }
+    }
+}
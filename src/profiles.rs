@@ -0,0 +1,247 @@
+use std::path::Path;
+use std::io::{BufReader, Write};
+use std::fs::File;
+use quick_xml::events::Event;
+use quick_xml::{Reader, Writer};
+
+use crate::rules::Rule;
+use crate::mercopinions::{MercOpinions, readAnOpinion, setOpinion, checkOpinionId};
+use crate::{createOutput, parseString, parseu8};
+
+
+// Newer builds keep the merc data in MercProfiles.xml, with each profile's opinions in an
+// <Opinions> block of the same AnOpinion elements MercOpinions.xml uses.
+pub struct MercProfiles
+{
+    pub index: Vec<u8>,
+    pub nicknames: Vec<String>,
+    pub opinions: Vec<Vec<i32>>
+}
+impl MercProfiles
+{
+    pub const FILE: &'static str = "MercProfiles.xml";
+
+    pub fn new() -> MercProfiles
+    {
+        let index = Vec::new();
+        let nicknames = Vec::new();
+        let opinions = Vec::new();
+
+        MercProfiles{index, nicknames, opinions}
+    }
+
+    pub fn loadMercProfiles(filepath: &Path) -> Result<MercProfiles, String>
+    {
+        let mut profiles = MercProfiles::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(element) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), element)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) if element.name().as_ref() == b"PROFILE" =>
+                {
+                    profiles.readItem(&mut reader, &mut buf).map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+        Ok(profiles)
+    }
+
+    /// The opinion blocks of all profiles as a MercOpinions table
+    pub fn toMercOpinions(&self) -> MercOpinions
+    {
+        let mut mercOpinions = MercOpinions::new();
+        mercOpinions.index = self.index.clone();
+        mercOpinions.nicknames = self.nicknames.clone();
+        mercOpinions.opinions = self.opinions.clone();
+        mercOpinions
+    }
+
+    /// Copies `source` to `filepath`, replacing the opinion block of every profile with the matching
+    /// record from `mercOpinions`. Records are matched by uiIndex, or by zNickname if the index is missing.
+    /// Everything else in the file is written back as it was.
+    pub fn injectOpinions(source: &Path, filepath: &Path, mercOpinions: &MercOpinions, rule: &Rule) -> Result<(), String>
+    {
+        let mut reader = Reader::from_file(source)
+            .map_err(|e| format!("Could not open file {}: {}", source.display(), e))?;
+        let readError = |position: usize, e: quick_xml::Error| format!("{}: Error at position {}: {:?}", source.display(), position, e);
+        let mut writer = Writer::new(Vec::new());
+
+        let mut buf = Vec::new();
+        let mut inProfile = false;
+        let mut skipping = false;
+        let mut current = Vec::new();
+        let mut uiIndex: Option<u8> = None;
+        let mut nickname = String::new();
+        // Whitespace is held back so it can be dropped together with a removed opinion block
+        let mut whitespace: Option<Vec<u8>> = None;
+        // The profile's own opinion block, written back if there is no record for the profile
+        let mut original = Writer::new(Vec::new());
+        loop
+        {
+            let event = match reader.read_event_into(&mut buf)
+            {
+                Err(e) => return Err(readError(reader.buffer_position(), e)),
+                Ok(Event::Eof) => break,
+                Ok(event) => event
+            };
+
+            if skipping
+            {
+                if let Event::End(ref element) = event
+                {
+                    if element.name().as_ref() == b"Opinions" { skipping = false; }
+                }
+                original.write_event(event).map_err(|e| readError(reader.buffer_position(), e))?;
+                buf.clear();
+                continue;
+            }
+
+            match event
+            {
+                Event::Text(ref e) if e.iter().all(|b| b.is_ascii_whitespace()) =>
+                {
+                    whitespace = Some(e.to_vec());
+                    buf.clear();
+                    continue;
+                }
+                Event::Start(ref element) | Event::Empty(ref element) if inProfile && element.name().as_ref() == b"Opinions" =>
+                {
+                    skipping = matches!(event, Event::Start(_));
+                    original.inner().extend(whitespace.take().unwrap_or_default());
+                    original.write_event(event).map_err(|e| readError(reader.buffer_position(), e))?;
+                    buf.clear();
+                    continue;
+                }
+                Event::Start(ref element) =>
+                {
+                    if element.name().as_ref() == b"PROFILE"
+                    {
+                        inProfile = true;
+                        uiIndex = None;
+                        nickname.clear();
+                        original.inner().clear();
+                    }
+                    current = element.name().as_ref().to_vec();
+                }
+                Event::Text(ref e) if inProfile =>
+                {
+                    let value = e.unescape().map_err(|e| readError(reader.buffer_position(), e))?.into_owned();
+                    match current.as_slice()
+                    {
+                        b"uiIndex" => uiIndex = value.trim().parse().ok(),
+                        b"zNickname" => nickname = value,
+                        _ => {}
+                    }
+                }
+                Event::End(ref element) if inProfile && element.name().as_ref() == b"PROFILE" =>
+                {
+                    inProfile = false;
+                    let position = uiIndex.and_then(|i| mercOpinions.position(i))
+                        .or_else(|| mercOpinions.nicknames.iter().position(|n| n.eq_ignore_ascii_case(&nickname)));
+                    match position
+                    {
+                        Some(k) =>
+                        {
+                            if !mercOpinions.nicknames[k].eq_ignore_ascii_case(&nickname)
+                            {
+                                println!("Warning: profile {:?} '{}' got the opinions of '{}'", uiIndex, nickname, mercOpinions.nicknames[k]);
+                            }
+                            writeOpinionBlock(writer.inner(), &mercOpinions.opinions[k], rule);
+                        }
+                        None =>
+                        {
+                            println!("Warning: no opinions for profile {:?} '{}', its own opinions are kept", uiIndex, nickname);
+                            writer.inner().extend_from_slice(original.inner());
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            if let Some(ws) = whitespace.take()
+            {
+                writer.inner().extend(ws);
+            }
+            writer.write_event(event).map_err(|e| readError(reader.buffer_position(), e))?;
+            buf.clear();
+        }
+        if let Some(ws) = whitespace.take()
+        {
+            writer.inner().extend(ws);
+        }
+
+        let mut file = createOutput(filepath)?;
+        file.write_all(&writer.into_inner())
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    pub fn readItem(&mut self, reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>) -> Result<(), String>
+	{
+		self.index.push(0);
+		self.nicknames.push(String::new());
+		self.opinions.push(Vec::new());
+		loop
+		{
+			match reader.read_event_into(buf)
+			{
+				    Ok(Event::Start(e)) =>
+				    {
+					        let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+					        match e.name().as_ref()
+					        {
+			            		b"uiIndex" => { *self.index.last_mut().unwrap() = parseu8(reader, buf, &name)?; }
+						        b"zNickname" => { *self.nicknames.last_mut().unwrap() = parseString(reader, buf, b"zNickname")?; }
+								_ => {}
+						        }
+				    }
+				    Ok(Event::Empty(e)) =>
+				    {
+					        if let Some((n, value)) = readAnOpinion(&e)
+					        {
+						        setOpinion(self.opinions.last_mut().unwrap(), checkOpinionId(n)?, value);
+					        }
+				    }
+
+				    Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+				    Ok(Event::Eof) => return Err("Unclosed PROFILE at end of file".to_string()),
+				    Ok(Event::End(ref element)) if element.name().as_ref() == b"PROFILE" => break,
+				    _ => (),
+			}
+			buf.clear();
+		}
+		Ok(())
+	}
+}
+
+
+fn writeOpinionBlock(buffer: &mut Vec<u8>, opinions: &[i32], rule: &Rule)
+{
+    let mut block = Vec::new();
+    for (j, value) in opinions.iter().enumerate()
+    {
+        let value = value.to_string();
+        if !rule.skips(&value)
+        {
+            write!(block, "\n\t\t\t<{}/>", rule.render(j as u32, &value)).unwrap();
+        }
+    }
+
+    // Mercs without opinions get no block at all
+    if !block.is_empty()
+    {
+        write!(buffer, "\n\t\t<Opinions>").unwrap();
+        buffer.extend_from_slice(&block);
+        write!(buffer, "\n\t\t</Opinions>").unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+use crate::mercopinions::MercOpinions;
+use crate::diff::modifier;
+
+
+//-----------------------------------------------------------------------------
+// Lookups
+//-----------------------------------------------------------------------------
+// Opinions are listed with the mercs' nicknames instead of uiIndices, missing opinions are 0.
+
+fn opinion(mercOpinions: &MercOpinions, merc: u8, target: u8) -> i32
+{
+    mercOpinions.position(merc)
+        .and_then(|k| mercOpinions.opinions[k].get(target as usize).copied())
+        .unwrap_or(0)
+}
+
+/// The opinions `merc` has of others and the opinions others have of `merc`, strongest first
+pub fn queryMerc(mercOpinions: &MercOpinions, merc: u8) -> Vec<String>
+{
+    let mut lines = vec![format!("{} ({})", mercOpinions.name(merc), merc)];
+
+    let mut of: Vec<(u8, i32)> = match mercOpinions.position(merc)
+    {
+        Some(k) => mercOpinions.opinions[k].iter().enumerate()
+            .filter(|(j, value)| **value != 0 && *j != merc as usize)
+            .filter_map(|(j, value)| u8::try_from(j).ok().map(|j| (j, *value)))
+            .collect(),
+        None =>
+        {
+            lines.push("  has no record in the file".to_string());
+            Vec::new()
+        }
+    };
+    let mut by: Vec<(u8, i32)> = mercOpinions.index.iter()
+        .filter(|other| **other != merc)
+        .map(|other| (*other, opinion(mercOpinions, *other, merc)))
+        .filter(|(_, value)| *value != 0)
+        .collect();
+    of.sort_by_key(|(_, value)| -value.abs());
+    by.sort_by_key(|(_, value)| -value.abs());
+
+    lines.push(format!("Opinions of {}:", mercOpinions.name(merc)));
+    lines.extend(of.iter().map(|(target, value)| format!("  -> {} ({}): {}", mercOpinions.name(*target), target, modifier(*value))));
+    lines.push(format!("Opinions about {}:", mercOpinions.name(merc)));
+    lines.extend(by.iter().map(|(other, value)| format!("  {} ({}): {}", mercOpinions.name(*other), other, modifier(*value))));
+    lines
+}
+
+/// The opinions of two mercs about each other
+pub fn queryPair(mercOpinions: &MercOpinions, a: u8, b: u8) -> Vec<String>
+{
+    vec![
+        format!("{} -> {}: {}", mercOpinions.name(a), mercOpinions.name(b), modifier(opinion(mercOpinions, a, b))),
+        format!("{} -> {}: {}", mercOpinions.name(b), mercOpinions.name(a), modifier(opinion(mercOpinions, b, a)))
+    ]
+}
@@ -0,0 +1,203 @@
+use std::collections::HashSet;
+use std::path::Path;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::Deserialize;
+
+use crate::localized::splitLanguage;
+use crate::rules::matchesFile;
+
+
+//-----------------------------------------------------------------------------
+// Cross-table references
+//-----------------------------------------------------------------------------
+// Which fields point at records of other tables is described in rules/references.toml.
+
+const BUILTIN_REFERENCES: &str = include_str!("../rules/references.toml");
+
+#[derive(Deserialize)]
+pub struct ReferenceSet
+{
+    #[serde(default, rename = "reference")]
+    pub references: Vec<Reference>
+}
+impl ReferenceSet
+{
+    pub fn builtin() -> Result<ReferenceSet, String>
+    {
+        toml::from_str(BUILTIN_REFERENCES).map_err(|e| format!("Error parsing builtin references\n {}", e))
+    }
+
+    /// Checks the references of every file in `dirpath`. Returns the broken references, and notes
+    /// about references that could not be checked because their target file is missing.
+    pub fn checkDirectory(&self, dirpath: &Path) -> Result<(Vec<String>, Vec<String>), String>
+    {
+        let mut files: Vec<String> = std::fs::read_dir(dirpath)
+            .map_err(|e| format!("Could not read directory {}: {}", dirpath.display(), e))?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        files.sort();
+
+        let mut broken = Vec::new();
+        let mut notes = Vec::new();
+        let mut targets: Vec<(&str, &str, &str, HashSet<String>)> = Vec::new();
+
+        for reference in &self.references
+        {
+            // Localized files hold the same references as their base file
+            let sources: Vec<&String> = files.iter()
+                .filter(|f| splitLanguage(f).0.is_none() && matchesFile(&reference.file, f))
+                .collect();
+            if sources.is_empty() { continue; }
+
+            let target = match files.iter().find(|f| f.eq_ignore_ascii_case(&reference.target))
+            {
+                Some(target) => target,
+                None =>
+                {
+                    notes.push(format!("{} not found, references from {} were not checked", reference.target, reference.file));
+                    continue;
+                }
+            };
+            let keys = match targets.iter().position(|(f, r, k, _)| *f == target && *r == reference.targetrecord && *k == reference.key)
+            {
+                Some(k) => &targets[k].3,
+                None =>
+                {
+                    let keys = readRecords(&dirpath.join(target), &reference.targetrecord)?.into_iter()
+                        .filter_map(|record| record.into_iter().find(|(name, _)| *name == reference.key).map(|(_, value)| value))
+                        .collect();
+                    targets.push((target, &reference.targetrecord, &reference.key, keys));
+                    &targets.last().unwrap().3
+                }
+            };
+
+            for source in sources
+            {
+                for (position, record) in readRecords(&dirpath.join(source), &reference.record)?.iter().enumerate()
+                {
+                    for (name, value) in record
+                    {
+                        let index = match reference.fields.iter().find_map(|f| matchField(f, name))
+                        {
+                            Some(index) => index,
+                            None => continue
+                        };
+                        if reference.none.iter().any(|n| n == value) { continue; }
+
+                        let key = match (reference.tagindex, index)
+                        {
+                            (true, Some(n)) => n.to_string(),
+                            _ => value.clone()
+                        };
+                        if !keys.contains(&key)
+                        {
+                            broken.push(format!("{}: {} {}: {} = {} refers to {} {}, which does not exist in {}",
+                                source, reference.record, position + 1, name, value, reference.key, key, target));
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok((broken, notes))
+    }
+}
+
+
+#[derive(Deserialize)]
+pub struct Reference
+{
+    /// File name, may contain one * wildcard
+    pub file: String,
+    pub record: String,
+    pub fields: Vec<String>,
+    /// The number in the field name is the reference, not the value
+    #[serde(default)]
+    pub tagindex: bool,
+    /// Values that mean no reference
+    #[serde(default)]
+    pub none: Vec<String>,
+    pub target: String,
+    pub targetrecord: String,
+    /// Field of the target records the reference is matched against
+    #[serde(default = "defaultKey")]
+    pub key: String
+}
+
+fn defaultKey() -> String { "uiIndex".to_string() }
+
+
+/// Matches `name` against a field pattern. Returns the field number for {n} patterns.
+fn matchField(pattern: &str, name: &str) -> Option<Option<u32>>
+{
+    match pattern.split_once("{n}")
+    {
+        Some((prefix, suffix)) =>
+        {
+            let number = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) { return None; }
+            Some(number.parse().ok())
+        }
+        None if pattern == name => Some(None),
+        None => None
+    }
+}
+
+/// Fields of every `record` in `filepath`. Text fields at any depth are listed by name, attributes of
+/// empty elements as Element.attribute.
+pub fn readRecords(filepath: &Path, record: &str) -> Result<Vec<Vec<(String, String)>>, String>
+{
+    let mut records: Vec<Vec<(String, String)>> = Vec::new();
+
+    let mut reader = Reader::from_file(filepath)
+        .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+    let readError = |position: usize, e: quick_xml::Error| format!("{}: Error at position {}: {:?}", filepath.display(), position, e);
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut depth = 0;
+    let mut current = String::new();
+    loop
+    {
+        match reader.read_event_into(&mut buf)
+        {
+            Err(e) => return Err(readError(reader.buffer_position(), e)),
+            Ok(Event::Eof) => break,
+
+            Ok(Event::Start(ref element)) =>
+            {
+                if depth > 0 { depth += 1; }
+                else if element.name().as_ref() == record.as_bytes()
+                {
+                    depth = 1;
+                    records.push(Vec::new());
+                }
+                current = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+            }
+            Ok(Event::Text(ref e)) if depth > 1 =>
+            {
+                let value = e.unescape().map_err(|e| readError(reader.buffer_position(), e))?.trim().to_string();
+                records.last_mut().unwrap().push((current.clone(), value));
+            }
+            Ok(Event::Empty(ref element)) if depth > 0 =>
+            {
+                let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                for attribute in element.attributes()
+                {
+                    let attribute = attribute.map_err(|e| readError(reader.buffer_position(), e.into()))?;
+                    let key = String::from_utf8_lossy(attribute.key.as_ref());
+                    let value = attribute.unescape_value().map_err(|e| readError(reader.buffer_position(), e))?.trim().to_string();
+                    records.last_mut().unwrap().push((format!("{}.{}", name, key), value));
+                }
+            }
+            Ok(Event::End(_)) if depth > 0 => depth -= 1,
+            _ => ()
+        }
+        buf.clear();
+    }
+
+    Ok(records)
+}
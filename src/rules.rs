@@ -0,0 +1,490 @@
+use std::path::{Path, PathBuf};
+use std::io::{self, BufRead, BufWriter, ErrorKind, Write};
+use std::fs::File;
+use std::ops::RangeInclusive;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::Deserialize;
+
+use crate::localized::splitLanguage;
+
+//-----------------------------------------------------------------------------
+// Declarative conversion rules
+//-----------------------------------------------------------------------------
+// Most legacy tables store lists as numbered tags (Opinion0..Opinion254),
+// which the newer format replaces with repeated elements carrying the number
+// as an attribute. A rule set describes those mappings per file, so new tables
+// can be converted without touching the code.
+
+const BUILTIN_RULES: &str = include_str!("../rules/default.toml");
+
+#[derive(Deserialize)]
+pub struct RuleSet
+{
+    #[serde(default, rename = "table")]
+    pub tables: Vec<TableRule>
+}
+impl RuleSet
+{
+    pub fn builtin() -> RuleSet
+    {
+        match toml::from_str(BUILTIN_RULES)
+        {
+            Ok(rules) => rules,
+            Err(e) => panic!("Error parsing builtin conversion rules\n {}", e)
+        }
+    }
+
+    pub fn load(filepath: &Path) -> Result<RuleSet, String>
+    {
+        let text = std::fs::read_to_string(filepath)
+            .map_err(|e| format!("Could not read rules file {}: {}", filepath.display(), e))?;
+        toml::from_str(&text)
+            .map_err(|e| format!("Error parsing rules file {}: {}", filepath.display(), e))
+    }
+
+    /// Builtin rules extended by the rules in `filepath`. User tables take precedence.
+    pub fn withUserRules(filepath: &Path) -> Result<RuleSet, String>
+    {
+        let mut rules = RuleSet::load(filepath)?;
+        for table in RuleSet::builtin().tables
+        {
+            if !rules.tables.iter().any(|t| t.file.eq_ignore_ascii_case(&table.file)) { rules.tables.push(table); }
+        }
+        Ok(rules)
+    }
+
+    /// Table for `filename`. Localized files use the table of their base file.
+    pub fn table(&self, filename: &str) -> Option<&TableRule>
+    {
+        let (_, filename) = splitLanguage(filename);
+        self.tables.iter().find(|t| t.matches(filename))
+    }
+
+    pub fn forFile(&self, filepath: &Path) -> Option<&TableRule>
+    {
+        let filename = filepath.file_name()?.to_str()?;
+        self.table(filename)
+    }
+
+    /// Table for a source file or a converted one, named "<name> out.xml" or by the table's output option
+    pub fn forOutput(&self, filepath: &Path) -> Option<&TableRule>
+    {
+        if let Some(table) = self.forFile(filepath) { return Some(table); }
+
+        let filename = filepath.file_name()?.to_str()?;
+        let (_, filename) = splitLanguage(filename);
+        if let Some(table) = self.tables.iter().find(|t| t.output.as_ref().is_some_and(|o| o.eq_ignore_ascii_case(filename)))
+        {
+            return Some(table);
+        }
+        let (stem, extension) = filename.rsplit_once('.')?;
+        self.table(&format!("{}.{}", stem.strip_suffix(" out")?, extension))
+    }
+}
+
+
+/// Case insensitive file name match, `pattern` may contain one * wildcard
+pub fn matchesFile(pattern: &str, filename: &str) -> bool
+{
+    let pattern = pattern.to_lowercase();
+    let filename = filename.to_lowercase();
+    match pattern.split_once('*')
+    {
+        Some((prefix, suffix)) => filename.len() >= prefix.len() + suffix.len()
+            && filename.starts_with(prefix) && filename.ends_with(suffix),
+        None => pattern == filename
+    }
+}
+
+
+#[derive(Deserialize)]
+pub struct TableRule
+{
+    /// File name, may contain one * wildcard for tables spread over several files
+    pub file: String,
+    pub root: String,
+    pub record: String,
+    /// Field holding the record index
+    #[serde(default = "defaultIndex")]
+    pub index: String,
+    /// Translatable text fields
+    #[serde(default)]
+    pub strings: Vec<String>,
+    pub output: Option<String>,
+    /// MercOpinions only: nickname comments after each opinion
+    #[serde(default)]
+    pub comments: bool,
+    /// MercOpinions only: opinion targets written by nickname instead of uiIndex
+    #[serde(default)]
+    pub symbolic: bool,
+    /// MercOpinions only: first and last uiIndex of the A.I.M. mercs
+    #[serde(default = "defaultAim")]
+    pub aim: [u8; 2],
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>
+}
+impl TableRule
+{
+    pub fn matches(&self, filename: &str) -> bool
+    {
+        matchesFile(&self.file, filename)
+    }
+
+    pub fn aimMercs(&self) -> RangeInclusive<u8>
+    {
+        self.aim[0]..=self.aim[1]
+    }
+
+    /// Output path next to the source file. Defaults to "<name> out.xml".
+    pub fn outputPath(&self, source: &Path) -> PathBuf
+    {
+        let mut path = source.to_path_buf();
+        path.pop();
+        match &self.output
+        {
+            Some(output) => path.push(output),
+            None =>
+            {
+                let stem = source.file_stem().unwrap().to_string_lossy();
+                path.push(format!("{} out.xml", stem));
+            }
+        }
+        path
+    }
+
+    /// Index field values of all records in `filepath`, in file order
+    pub fn readIndices(&self, filepath: &Path) -> Result<Vec<String>, String>
+    {
+        let mut indices = Vec::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut depth = 0;
+        let mut inIndex = false;
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(e) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) =>
+                {
+                    if depth > 0 { depth += 1; }
+                    else if element.name().as_ref() == self.record.as_bytes() { depth = 1; }
+                    inIndex = depth == 2 && element.name().as_ref() == self.index.as_bytes();
+                }
+                Ok(Event::Text(ref e)) if inIndex =>
+                {
+                    let value = e.unescape()
+                        .map_err(|e| format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e))?;
+                    indices.push(value.trim().to_string());
+                }
+                Ok(Event::End(_)) =>
+                {
+                    if depth > 0 { depth -= 1; }
+                    inIndex = false;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+
+        Ok(indices)
+    }
+
+    pub fn rule(&self, tag: &str) -> Option<(&Rule, u32)>
+    {
+        self.rules.iter().find_map(|r| r.matchTag(tag).map(|n| (r, n)))
+    }
+
+    /// Converts a legacy file by copying each record and rewriting the tags matched by the rules.
+    /// Records are written as they are read, so memory use does not grow with the file.
+    pub fn convert(&self, source: &Path, filepath: &Path) -> Result<(), String>
+    {
+        let reader = Reader::from_file(source)
+            .map_err(|e| format!("Could not open file {}: {}", source.display(), e))?;
+
+        println!("{}", filepath.display());
+        let mut writer = std::fs::create_dir_all(filepath.parent().unwrap())
+            .and_then(|_| File::create(filepath))
+            .map(BufWriter::new)
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))?;
+        match self.convertRecords(reader, &mut writer).and_then(|_| writer.flush())
+        {
+            Ok(_) => Ok(()),
+            // Broken source xml, no half written output is left behind
+            Err(e) if e.kind() == ErrorKind::InvalidData =>
+            {
+                drop(writer);
+                let _ = std::fs::remove_file(filepath);
+                Err(format!("{}: {}", source.display(), e))
+            }
+            Err(e) => Err(format!("Could not write file {}: {}", filepath.display(), e))
+        }
+    }
+
+    /// Writes the converted records of `reader` to `writer`, one record at a time
+    pub fn convertRecords<R: BufRead, W: Write>(&self, mut reader: Reader<R>, writer: &mut W) -> io::Result<()>
+    {
+        reader.trim_text(true);
+        writeln!(writer, "<{}>", self.root)?;
+
+        let mut buf = Vec::new();
+        // Open elements inside the current record: name, start tag with its attributes and whether
+        // the start tag has been written yet
+        let mut open: Vec<(String, String, bool)> = Vec::new();
+        let mut inRecord = false;
+        let mut text = String::new();
+        // Text of the current field came from a CDATA section, which is written back as one
+        let mut cdata = false;
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(e) => return Err(io::Error::new(ErrorKind::InvalidData, format!("Error at position {}: {:?}", reader.buffer_position(), e))),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Comment(ref e)) =>
+                {
+                    // Comments inside a field go before it, the field is written when it ends
+                    let depth = match open.last()
+                    {
+                        Some((_, _, false)) => open.len(),
+                        _ if inRecord => open.len() + 1,
+                        _ => 0
+                    };
+                    writeln!(writer, "{}<!--{}-->", indent(depth), String::from_utf8_lossy(e))?;
+                }
+                Ok(Event::CData(ref e)) if inRecord =>
+                {
+                    text.push_str(&String::from_utf8_lossy(e));
+                    cdata = true;
+                }
+
+                Ok(Event::Start(ref element)) =>
+                {
+                    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                    if !inRecord
+                    {
+                        if name == self.record
+                        {
+                            inRecord = true;
+                            writeln!(writer, "\t<{}>", startTag(element))?;
+                        }
+                    }
+                    else
+                    {
+                        writeOpenParent(writer, &mut open)?;
+                        open.push((name, startTag(element), false));
+                        text.clear();
+                        cdata = false;
+                    }
+                }
+                Ok(Event::Text(ref e)) if inRecord =>
+                {
+                    let value = e.unescape().map_err(|e| io::Error::new(ErrorKind::InvalidData, format!("{:?}", e)))?;
+                    text.push_str(&value);
+                }
+                Ok(Event::Empty(ref element)) if inRecord =>
+                {
+                    writeOpenParent(writer, &mut open)?;
+                    writeln!(writer, "{}<{}/>", indent(open.len() + 1), startTag(element))?;
+                }
+                Ok(Event::End(_)) if inRecord =>
+                {
+                    match open.pop()
+                    {
+                        None =>
+                        {
+                            inRecord = false;
+                            writeln!(writer, "\t</{}>", self.record)?;
+                        }
+                        Some((name, _, true)) =>
+                        {
+                            writeln!(writer, "{}</{}>", indent(open.len() + 1), name)?;
+                        }
+                        Some((name, tag, false)) =>
+                        {
+                            let depth = indent(open.len() + 1);
+                            match self.rule(&name)
+                            {
+                                Some((rule, n)) =>
+                                {
+                                    if !rule.skips(&text)
+                                    {
+                                        writeln!(writer, "{}<{}/>", depth, rule.render(n, &text))?;
+                                    }
+                                }
+                                None if cdata =>
+                                {
+                                    writeln!(writer, "{}<{}><![CDATA[{}]]></{}>", depth, tag, text, name)?;
+                                }
+                                None =>
+                                {
+                                    writeln!(writer, "{}<{}>{}</{}>", depth, tag, escape(&text), name)?;
+                                }
+                            }
+                            text.clear();
+                            cdata = false;
+                        }
+                    }
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+
+        writeln!(writer, "</{}>", self.root)
+    }
+}
+
+
+#[derive(Deserialize)]
+pub struct Rule
+{
+    /// Legacy tag with a {n} placeholder for the number, eg. "Opinion{n}"
+    pub source: String,
+    /// New element with attributes, eg. 'AnOpinion id = "{n}" modifier = "{value}"'
+    pub target: String,
+    pub skip: Option<String>
+}
+impl Rule
+{
+    /// Returns the number in `tag` if it matches the source pattern.
+    pub fn matchTag(&self, tag: &str) -> Option<u32>
+    {
+        let (prefix, suffix) = self.source.split_once("{n}")?;
+        let digits = tag.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) { return None; }
+        digits.parse().ok()
+    }
+
+    pub fn skips(&self, value: &str) -> bool
+    {
+        value.is_empty() || self.skip.as_deref() == Some(value)
+    }
+
+    pub fn render(&self, n: u32, value: &str) -> String
+    {
+        self.target.replace("{n}", &n.to_string()).replace("{value}", &escape(value))
+    }
+
+    /// `render` with the number attribute replaced by the target's nickname, eg.
+    /// 'AnOpinion target = "Fox" modifier = "5"' for 'AnOpinion id = "{n}" modifier = "{value}"'
+    pub fn renderSymbolic(&self, target: &str, value: &str) -> String
+    {
+        let name = self.target.split_whitespace().next().unwrap_or_default();
+        let template = BytesStart::from_content(self.target.as_str(), name.len());
+        let mut element = name.to_string();
+        for attribute in template.attributes().flatten()
+        {
+            match attribute.value.as_ref()
+            {
+                b"{n}" => element += &format!(" target = \"{}\"", escape(target)),
+                text =>
+                {
+                    let key = String::from_utf8_lossy(attribute.key.as_ref());
+                    let text = String::from_utf8_lossy(text).replace("{value}", &escape(value));
+                    element += &format!(" {} = \"{}\"", key, text);
+                }
+            }
+        }
+        element
+    }
+
+    /// Reverse of `render`. Returns the number and value of `element` if it is a target element of this rule.
+    pub fn matchTarget(&self, element: &BytesStart) -> Option<(u32, String)>
+    {
+        let (n, value) = self.matchAttributes(element, None)?;
+        Some((n.trim().parse().ok()?, value))
+    }
+
+    /// Reverse of `renderSymbolic`. Returns the target nickname and value.
+    pub fn matchSymbolic(&self, element: &BytesStart) -> Option<(String, String)>
+    {
+        let (target, value) = self.matchAttributes(element, Some(b"target"))?;
+        Some((target.trim().to_string(), value))
+    }
+
+    // Values of the {n} attribute, or of `numberKey` in its place, and of the {value} attribute
+    fn matchAttributes(&self, element: &BytesStart, numberKey: Option<&[u8]>) -> Option<(String, String)>
+    {
+        let name = self.target.split_whitespace().next()?;
+        if element.name().as_ref() != name.as_bytes() { return None; }
+
+        let template = BytesStart::from_content(self.target.as_str(), name.len());
+        let mut n = None;
+        let mut value = None;
+        for attribute in template.attributes().flatten()
+        {
+            let key = match (attribute.value.as_ref(), numberKey)
+            {
+                (b"{n}", Some(key)) => key,
+                _ => attribute.key.as_ref()
+            };
+            let found = element.try_get_attribute(key).ok().flatten()?;
+            let found = found.unescape_value().ok()?.into_owned();
+            match attribute.value.as_ref()
+            {
+                b"{n}" => n = Some(found),
+                b"{value}" => value = Some(found),
+                _ => {}
+            }
+        }
+        Some((n?, value?))
+    }
+
+    /// Tag of the old format for number `n`, eg. "Opinion17"
+    pub fn sourceTag(&self, n: u32) -> String
+    {
+        self.source.replace("{n}", &n.to_string())
+    }
+}
+
+
+// Parent of a nested element is a container, so its start tag goes out before the child
+fn writeOpenParent<W: Write>(writer: &mut W, open: &mut [(String, String, bool)]) -> io::Result<()>
+{
+    let depth = open.len();
+    if let Some((_, parent, written)) = open.last_mut()
+    {
+        if !*written
+        {
+            writeln!(writer, "{}<{}>", indent(depth), parent)?;
+            *written = true;
+        }
+    }
+    Ok(())
+}
+
+// Name and attributes of a start tag as they appear in the source, elements that no rule matches
+// are copied through unchanged
+fn startTag(element: &BytesStart) -> String
+{
+    String::from_utf8_lossy(element).trim_end().to_string()
+}
+
+fn defaultIndex() -> String
+{
+    "uiIndex".to_string()
+}
+
+fn defaultAim() -> [u8; 2]
+{
+    [0, 39]
+}
+
+fn indent(depth: usize) -> String
+{
+    "\t".repeat(depth + 1)
+}
+
+fn escape(value: &str) -> String
+{
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('"', "&quot;")
+}
@@ -0,0 +1,488 @@
+use std::path::Path;
+use std::io::Write;
+use std::fs::File;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::{json, Map, Value};
+
+use crate::mercopinions::{MercOpinions, MAX_OPINION_ID};
+use crate::rules::TableRule;
+
+
+//-----------------------------------------------------------------------------
+// Schemas of the new format
+//-----------------------------------------------------------------------------
+// A table schema is made from the conversion rules. MercOpinions records are fully described, for
+// other tables only the index field and the elements written by the rules are known; their other
+// fields are allowed as they are. The same schema is written as XSD, as JSON Schema for the JSON
+// and YAML documents, and used by validate.
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind
+{
+    Text,
+    Integer,
+    Index,
+    Byte,
+    /// Opinion target, a merc id the game can hold
+    OpinionId,
+    /// Opinion modifier, stored as INT8 by the game
+    Modifier
+}
+impl Kind
+{
+    fn xsd(&self) -> &'static str
+    {
+        match self
+        {
+            Kind::Text => "xs:string",
+            Kind::Integer => "xs:integer",
+            Kind::Index => "xs:nonNegativeInteger",
+            Kind::Byte => "xs:unsignedByte",
+            Kind::OpinionId => "opinionId",
+            Kind::Modifier => "xs:byte"
+        }
+    }
+
+    fn json(&self) -> Value
+    {
+        match self
+        {
+            Kind::Text => json!({"type": "string"}),
+            Kind::Integer => json!({"type": "integer"}),
+            Kind::Index => json!({"type": "integer", "minimum": 0}),
+            Kind::Byte => json!({"type": "integer", "minimum": 0, "maximum": 255}),
+            Kind::OpinionId => json!({"type": "integer", "minimum": 0, "maximum": MAX_OPINION_ID}),
+            Kind::Modifier => json!({"type": "integer", "minimum": i8::MIN, "maximum": i8::MAX})
+        }
+    }
+
+    fn check(&self, value: &str) -> Result<(), String>
+    {
+        let value = value.trim();
+        let valid = match self
+        {
+            Kind::Text => true,
+            Kind::Integer => value.parse::<i64>().is_ok(),
+            Kind::Index => value.parse::<u64>().is_ok(),
+            Kind::Byte => value.parse::<u8>().is_ok(),
+            Kind::OpinionId => value.parse::<usize>().is_ok_and(|n| n <= MAX_OPINION_ID),
+            Kind::Modifier => value.parse::<i8>().is_ok()
+        };
+        match (valid, self)
+        {
+            (true, _) => Ok(()),
+            (false, Kind::Byte) => Err(format!("\"{}\" is not a number from 0 to 255", value)),
+            (false, Kind::OpinionId) => Err(format!("\"{}\" is not a number from 0 to {}", value, MAX_OPINION_ID)),
+            (false, Kind::Modifier) => Err(format!("\"{}\" is not a number from {} to {}", value, i8::MIN, i8::MAX)),
+            (false, Kind::Index) => Err(format!("\"{}\" is not a number of 0 or more", value)),
+            (false, _) => Err(format!("\"{}\" is not a whole number", value))
+        }
+    }
+}
+
+pub struct Attribute
+{
+    pub name: String,
+    pub kind: Kind,
+    pub required: bool
+}
+
+pub struct Field
+{
+    pub name: String,
+    /// Kind of the text, None for elements without text
+    pub text: Option<Kind>,
+    pub attributes: Vec<Attribute>,
+    /// Attributes of which exactly one is needed
+    pub oneOf: Vec<String>,
+    pub min: u32,
+    /// None for any number
+    pub max: Option<u32>
+}
+impl Field
+{
+    fn text(name: &str, kind: Kind, min: u32) -> Field
+    {
+        Field{name: name.to_string(), text: Some(kind), attributes: Vec::new(), oneOf: Vec::new(), min, max: Some(1)}
+    }
+}
+
+pub struct TableSchema
+{
+    pub file: String,
+    pub root: String,
+    pub record: String,
+    pub fields: Vec<Field>,
+    /// Records may hold other fields than the listed ones, in any order
+    pub open: bool
+}
+impl TableSchema
+{
+    pub fn fromTable(table: &TableRule) -> TableSchema
+    {
+        let mut schema = TableSchema{file: table.file.clone(), root: table.root.clone(), record: table.record.clone(), fields: Vec::new(), open: true};
+
+        if table.file.eq_ignore_ascii_case(MercOpinions::FILE)
+        {
+            schema.open = false;
+            schema.fields.push(Field::text("uiIndex", Kind::Byte, 1));
+            schema.fields.push(Field::text("zNickname", Kind::Text, 0));
+            schema.fields.push(Field{
+                name: "AnOpinion".to_string(),
+                text: None,
+                attributes: vec![
+                    Attribute{name: "id".to_string(), kind: Kind::OpinionId, required: false},
+                    Attribute{name: "target".to_string(), kind: Kind::Text, required: false},
+                    Attribute{name: "modifier".to_string(), kind: Kind::Modifier, required: true}
+                ],
+                oneOf: vec!["id".to_string(), "target".to_string()],
+                min: 0,
+                max: None
+            });
+            return schema;
+        }
+
+        schema.fields.push(Field::text(&table.index, Kind::Index, 0));
+        for rule in &table.rules
+        {
+            // The {n} attribute is the number from the legacy tag, the {value} one its text
+            let name = match rule.target.split_whitespace().next()
+            {
+                Some(name) => name,
+                None => continue
+            };
+            let template = BytesStart::from_content(rule.target.as_str(), name.len());
+            let attributes = template.attributes().flatten().map(|a|
+            {
+                let placeholder = String::from_utf8_lossy(&a.value).into_owned();
+                let kind = if placeholder == "{n}" { Kind::Index } else { Kind::Text };
+                Attribute{name: String::from_utf8_lossy(a.key.as_ref()).into_owned(), kind, required: true}
+            }).collect();
+            schema.fields.push(Field{name: name.to_string(), text: None, attributes, oneOf: Vec::new(), min: 0, max: None});
+        }
+        schema
+    }
+
+    /// File name of the schema without extension, eg. "MercOpinions" or "Inventory" for "*Inventory.xml"
+    pub fn name(&self) -> String
+    {
+        let file = self.file.replace('*', "");
+        match file.rsplit_once('.')
+        {
+            Some((stem, _)) => stem.to_string(),
+            None => file
+        }
+    }
+
+    pub fn toXsd(&self) -> String
+    {
+        let mut xsd = String::new();
+        xsd += "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
+        xsd += &format!("<!-- Schema of {} in the new format, made by JA2-ConvertXMLData -->\n", self.file);
+        xsd += "<xs:schema xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">\n";
+        xsd += &format!("\t<xs:element name=\"{}\">\n", self.root);
+        xsd += "\t\t<xs:complexType>\n\t\t\t<xs:sequence>\n";
+        xsd += &format!("\t\t\t\t<xs:element name=\"{}\" minOccurs=\"0\" maxOccurs=\"unbounded\">\n", self.record);
+        xsd += "\t\t\t\t\t<xs:complexType>\n\t\t\t\t\t\t<xs:sequence>\n";
+        if self.open
+        {
+            // Known fields are declared globally below, so lax processing checks them wherever they are
+            xsd += "\t\t\t\t\t\t\t<xs:any processContents=\"lax\" minOccurs=\"0\" maxOccurs=\"unbounded\"/>\n";
+        }
+        else
+        {
+            for field in &self.fields
+            {
+                xsd += &xsdElement(field, 7, true);
+            }
+        }
+        xsd += "\t\t\t\t\t\t</xs:sequence>\n\t\t\t\t\t</xs:complexType>\n\t\t\t\t</xs:element>\n";
+        xsd += "\t\t\t</xs:sequence>\n\t\t</xs:complexType>\n\t</xs:element>\n";
+        if self.open
+        {
+            for field in &self.fields
+            {
+                xsd += &xsdElement(field, 1, false);
+            }
+        }
+        let kinds = self.fields.iter().flat_map(|f| f.text.iter().chain(f.attributes.iter().map(|a| &a.kind)));
+        if kinds.into_iter().any(|k| *k == Kind::OpinionId)
+        {
+            xsd += "\t<xs:simpleType name=\"opinionId\">\n";
+            xsd += "\t\t<xs:restriction base=\"xs:unsignedByte\">\n";
+            xsd += &format!("\t\t\t<xs:maxInclusive value=\"{}\"/>\n", MAX_OPINION_ID);
+            xsd += "\t\t</xs:restriction>\n\t</xs:simpleType>\n";
+        }
+        xsd += "</xs:schema>\n";
+        xsd
+    }
+
+    /// JSON Schema of the JSON and YAML documents, see document.rs
+    pub fn toJsonSchema(&self) -> Value
+    {
+        let record = if self.open
+        {
+            // Generic documents hold every value as text
+            let attributes = json!({"type": "object", "additionalProperties": {"type": "string"}});
+            let mut properties = Map::new();
+            for field in &self.fields
+            {
+                let property = match field.text
+                {
+                    Some(_) => json!({"type": "string"}),
+                    None =>
+                    {
+                        let required: Vec<&str> = field.attributes.iter().filter(|a| a.required).map(|a| a.name.as_str()).collect();
+                        json!({"type": "array", "items": {"type": "object", "required": required, "additionalProperties": {"type": "string"}}})
+                    }
+                };
+                properties.insert(field.name.clone(), property);
+            }
+            json!({
+                "type": "object",
+                "properties": properties,
+                "additionalProperties": {"oneOf": [{"type": "string"}, {"type": "array", "items": attributes}]}
+            })
+        }
+        else
+        {
+            // MercOpinions documents, see MercOpinionsDocument
+            json!({
+                "type": "object",
+                "required": ["uiIndex"],
+                "properties": {
+                    "uiIndex": Kind::Byte.json(),
+                    "zNickname": Kind::Text.json(),
+                    "opinions": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["id", "modifier"],
+                            "properties": {"id": Kind::OpinionId.json(), "modifier": Kind::Modifier.json()},
+                            "additionalProperties": false
+                        }
+                    }
+                },
+                "additionalProperties": false
+            })
+        };
+
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": format!("{} in the JSON and YAML format of JA2-ConvertXMLData", self.file),
+            "type": "object",
+            "required": ["table", "records"],
+            "properties": {
+                "table": {"type": "string"},
+                "records": {"type": "array", "items": record}
+            }
+        })
+    }
+
+    /// Checks a new format xml file. Returns one message per problem, with the line it is on.
+    pub fn validate(&self, filepath: &Path) -> Result<Vec<String>, String>
+    {
+        let bytes = std::fs::read(filepath).map_err(|e| format!("Could not read {}: {}", filepath.display(), e))?;
+        let filename = filepath.file_name().unwrap().to_string_lossy().into_owned();
+        let line = |position: usize| bytes[..position.min(bytes.len())].iter().filter(|b| **b == b'\n').count() + 1;
+
+        let mut reader = Reader::from_reader(bytes.as_slice());
+        reader.trim_text(true);
+
+        let mut errors = Vec::new();
+        let mut buf = Vec::new();
+        let mut depth = 0;
+        // Occurrences of each field in the current record, and the field the text belongs to
+        let mut counts = vec![0u32; self.fields.len()];
+        let mut last = 0;
+        let mut current: Option<usize> = None;
+        let mut text = String::new();
+        loop
+        {
+            let event = reader.read_event_into(&mut buf);
+            // Position after the event, before it is the end of the previous line
+            let at = line(reader.buffer_position());
+            let mut error = |message: String| errors.push(format!("{}:{}: {}", filename, at, message));
+            match event
+            {
+                Err(e) =>
+                {
+                    error(format!("{:?}", e));
+                    break;
+                }
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) | Ok(Event::Empty(ref element)) =>
+                {
+                    let empty = matches!(event, Ok(Event::Empty(_)));
+                    let name = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                    match depth
+                    {
+                        0 if name != self.root => error(format!("Root element is {}, not {}", name, self.root)),
+                        1 if name != self.record => error(format!("{} is not a {} record", name, self.record)),
+                        1 =>
+                        {
+                            counts.iter_mut().for_each(|c| *c = 0);
+                            last = 0;
+                        }
+                        2 => match self.fields.iter().position(|f| f.name == name)
+                        {
+                            Some(k) =>
+                            {
+                                let field = &self.fields[k];
+                                counts[k] += 1;
+                                if !self.open && k < last
+                                {
+                                    error(format!("{} must come before {}", name, self.fields[last].name));
+                                }
+                                last = last.max(k);
+                                for message in self.checkAttributes(field, element)
+                                {
+                                    error(message);
+                                }
+                                if field.text.is_some() && !empty
+                                {
+                                    current = Some(k);
+                                    text.clear();
+                                }
+                            }
+                            None if !self.open => error(format!("Unknown element {} in {}", name, self.record)),
+                            None => {}
+                        },
+                        _ if current.is_some() => error(format!("{} can't contain elements", self.fields[current.unwrap()].name)),
+                        _ => {}
+                    }
+                    if !empty { depth += 1; }
+                }
+                Ok(Event::Text(ref e)) =>
+                {
+                    match current
+                    {
+                        Some(_) => text.push_str(&e.unescape().unwrap_or_default()),
+                        None if depth == 2 && !self.open => error(format!("Text \"{}\" outside of a field", String::from_utf8_lossy(e))),
+                        None => {}
+                    }
+                }
+                Ok(Event::End(_)) =>
+                {
+                    depth -= 1;
+                    if depth == 2
+                    {
+                        if let Some(k) = current.take()
+                        {
+                            if let Err(message) = self.fields[k].text.unwrap().check(&text)
+                            {
+                                error(format!("{}: {}", self.fields[k].name, message));
+                            }
+                        }
+                    }
+                    if depth == 1
+                    {
+                        for (field, count) in self.fields.iter().zip(&counts)
+                        {
+                            if *count < field.min
+                            {
+                                error(format!("{} without {}", self.record, field.name));
+                            }
+                            if field.max.is_some_and(|max| *count > max)
+                            {
+                                error(format!("{} has {} {} fields, at most {} allowed", self.record, count, field.name, field.max.unwrap()));
+                            }
+                        }
+                    }
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+
+        Ok(errors)
+    }
+
+    fn checkAttributes(&self, field: &Field, element: &BytesStart) -> Vec<String>
+    {
+        let mut errors = Vec::new();
+        let mut found = Vec::new();
+        for attribute in element.attributes()
+        {
+            let attribute = match attribute
+            {
+                Ok(attribute) => attribute,
+                Err(e) =>
+                {
+                    errors.push(format!("{}: {:?}", field.name, e));
+                    continue;
+                }
+            };
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+            let value = attribute.unescape_value().map(|v| v.into_owned()).unwrap_or_default();
+            match field.attributes.iter().find(|a| a.name == key)
+            {
+                Some(a) =>
+                {
+                    if let Err(message) = a.kind.check(&value)
+                    {
+                        errors.push(format!("{} {}: {}", field.name, key, message));
+                    }
+                }
+                None if !self.open => errors.push(format!("{} has an unknown attribute {}", field.name, key)),
+                None => {}
+            }
+            found.push(key);
+        }
+        for attribute in field.attributes.iter().filter(|a| a.required && !found.contains(&a.name))
+        {
+            errors.push(format!("{} without {}", field.name, attribute.name));
+        }
+        if !field.oneOf.is_empty() && field.oneOf.iter().filter(|a| found.contains(a)).count() != 1
+        {
+            errors.push(format!("{} needs either {}", field.name, field.oneOf.join(" or ")));
+        }
+        errors
+    }
+}
+
+
+fn xsdElement(field: &Field, indent: usize, local: bool) -> String
+{
+    let tabs = "\t".repeat(indent);
+    let mut occurs = String::new();
+    if local
+    {
+        if field.min != 1 { occurs += &format!(" minOccurs=\"{}\"", field.min); }
+        match field.max
+        {
+            None => occurs += " maxOccurs=\"unbounded\"",
+            Some(1) => {}
+            Some(max) => occurs += &format!(" maxOccurs=\"{}\"", max)
+        }
+    }
+
+    if let Some(kind) = field.text
+    {
+        return format!("{}<xs:element name=\"{}\" type=\"{}\"{}/>\n", tabs, field.name, kind.xsd(), occurs);
+    }
+
+    let mut xsd = format!("{}<xs:element name=\"{}\"{}>\n", tabs, field.name, occurs);
+    if !field.oneOf.is_empty()
+    {
+        xsd += &format!("{}\t<xs:annotation><xs:documentation>Needs either {}</xs:documentation></xs:annotation>\n", tabs, field.oneOf.join(" or "));
+    }
+    xsd += &format!("{}\t<xs:complexType>\n", tabs);
+    for attribute in &field.attributes
+    {
+        let usage = if attribute.required { " use=\"required\"" } else { "" };
+        xsd += &format!("{}\t\t<xs:attribute name=\"{}\" type=\"{}\"{}/>\n", tabs, attribute.name, attribute.kind.xsd(), usage);
+    }
+    xsd += &format!("{}\t</xs:complexType>\n{}</xs:element>\n", tabs, tabs);
+    xsd
+}
+
+pub fn saveSchema(text: &str, filepath: &Path)
+{
+    println!("{}", &filepath.to_str().unwrap());
+    std::fs::create_dir_all(filepath.parent().unwrap()).unwrap();
+    let mut file = File::create(filepath).unwrap();
+    file.write_all(text.as_bytes()).unwrap();
+}
@@ -0,0 +1,467 @@
+use std::path::Path;
+use std::collections::HashMap;
+use std::io::Write;
+use quick_xml::events::{BytesText, Event};
+use quick_xml::escape::escape;
+use quick_xml::{Reader, Writer};
+
+use crate::rules::TableRule;
+use crate::localized::splitLanguage;
+use crate::createOutput;
+
+
+// Translatable strings of a table, keyed by "<file>/<index>/<field>", eg. "MercOpinions.xml/12/zNickname".
+// `source` is the text of the base file and `translation` the text of the localized file, if any.
+pub struct StringEntry
+{
+    pub key: String,
+    pub source: String,
+    pub translation: String
+}
+
+pub struct Translations
+{
+    pub entries: Vec<StringEntry>
+}
+impl Translations
+{
+    pub fn new() -> Translations
+    {
+        Translations{entries: Vec::new()}
+    }
+
+    /// Strings of `filepath`. For a localized file the source texts come from `basepath`.
+    pub fn extractStrings(filepath: &Path, basepath: Option<&Path>, table: &TableRule) -> Result<Translations, String>
+    {
+        let mut translations = Translations::new();
+        let file = baseName(filepath);
+
+        let strings = readStrings(filepath, table)?;
+        match basepath
+        {
+            Some(basepath) =>
+            {
+                let mut translated: HashMap<(&str, &str), &str> = HashMap::new();
+                for (index, field, text) in &strings
+                {
+                    translated.entry((index, field)).or_insert(text);
+                }
+                for (index, field, source) in readStrings(basepath, table)?
+                {
+                    let translation = translated.get(&(index.as_str(), field.as_str())).map(|t| t.to_string()).unwrap_or_default();
+                    translations.entries.push(StringEntry{key: key(&file, &index, &field), source, translation});
+                }
+            }
+            None =>
+            {
+                for (index, field, source) in strings
+                {
+                    translations.entries.push(StringEntry{key: key(&file, &index, &field), source, translation: String::new()});
+                }
+            }
+        }
+        Ok(translations)
+    }
+
+    pub fn load(filepath: &Path) -> Result<Translations, String>
+    {
+        let text = std::fs::read_to_string(filepath)
+            .map_err(|e| format!("Could not read file {}: {}", filepath.display(), e))?;
+        if isXliff(filepath) { Translations::parseXliff(&text) } else { Translations::parsePo(&text) }
+    }
+
+    /// Writes the strings as XLIFF if `xliff` is set, as a PO file otherwise
+    pub fn save(&self, filepath: &Path, xliff: bool) -> Result<(), String>
+    {
+        let mut buffer = Vec::new();
+        if xliff { self.writeXliff(&mut buffer, filepath); } else { self.writePo(&mut buffer); }
+
+        let mut file = createOutput(filepath)?;
+        file.write_all(&buffer)
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    fn writePo(&self, buffer: &mut Vec<u8>)
+    {
+        writeln!(buffer, "msgid \"\"").unwrap();
+        writeln!(buffer, "msgstr \"\"").unwrap();
+        writeln!(buffer, "\"Content-Type: text/plain; charset=UTF-8\\n\"").unwrap();
+
+        for entry in &self.entries
+        {
+            writeln!(buffer).unwrap();
+            writeln!(buffer, "msgctxt \"{}\"", poEscape(&entry.key)).unwrap();
+            writeln!(buffer, "msgid \"{}\"", poEscape(&entry.source)).unwrap();
+            writeln!(buffer, "msgstr \"{}\"", poEscape(&entry.translation)).unwrap();
+        }
+    }
+
+    fn writeXliff(&self, buffer: &mut Vec<u8>, filepath: &Path)
+    {
+        let original = filepath.file_stem().unwrap().to_string_lossy();
+        writeln!(buffer, "<?xml version=\"1.0\" encoding=\"utf-8\"?>").unwrap();
+        writeln!(buffer, "<xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">").unwrap();
+        writeln!(buffer, "\t<file original=\"{}\" source-language=\"en\" datatype=\"xml\">", escape(&original)).unwrap();
+        writeln!(buffer, "\t\t<body>").unwrap();
+        for entry in &self.entries
+        {
+            writeln!(buffer, "\t\t\t<trans-unit id=\"{}\">", escape(&entry.key)).unwrap();
+            writeln!(buffer, "\t\t\t\t<source>{}</source>", escape(&entry.source)).unwrap();
+            writeln!(buffer, "\t\t\t\t<target>{}</target>", escape(&entry.translation)).unwrap();
+            writeln!(buffer, "\t\t\t</trans-unit>").unwrap();
+        }
+        writeln!(buffer, "\t\t</body>").unwrap();
+        writeln!(buffer, "\t</file>").unwrap();
+        writeln!(buffer, "</xliff>").unwrap();
+    }
+
+    fn parsePo(text: &str) -> Result<Translations, String>
+    {
+        let mut translations = Translations::new();
+        let mut entry = StringEntry{key: String::new(), source: String::new(), translation: String::new()};
+        // Which string a continuation line "..." belongs to
+        let mut current = "";
+
+        for (number, line) in text.lines().enumerate()
+        {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+
+            let (keyword, quoted) = match line.split_once(' ')
+            {
+                Some((keyword, rest)) if !line.starts_with('"') => (keyword, rest.trim()),
+                _ => ("", line)
+            };
+            let value = poUnescape(quoted)
+                .ok_or_else(|| format!("Invalid string on line {}: {}", number + 1, line))?;
+
+            match keyword
+            {
+                "msgctxt" =>
+                {
+                    if !entry.key.is_empty() { translations.entries.push(entry); }
+                    entry = StringEntry{key: value, source: String::new(), translation: String::new()};
+                    current = "msgctxt";
+                }
+                "msgid" => { entry.source = value; current = "msgid"; }
+                "msgstr" => { entry.translation = value; current = "msgstr"; }
+                "" => match current
+                {
+                    "msgctxt" => entry.key.push_str(&value),
+                    "msgid" => entry.source.push_str(&value),
+                    "msgstr" => entry.translation.push_str(&value),
+                    _ => return Err(format!("Unexpected string on line {}", number + 1))
+                },
+                other => return Err(format!("Unknown keyword {} on line {}", other, number + 1))
+            }
+        }
+        if !entry.key.is_empty() { translations.entries.push(entry); }
+
+        Ok(translations)
+    }
+
+    fn parseXliff(text: &str) -> Result<Translations, String>
+    {
+        let mut translations = Translations::new();
+        let mut reader = Reader::from_str(text);
+        reader.trim_text(true);
+
+        let mut current = Vec::new();
+        loop
+        {
+            match reader.read_event()
+            {
+                Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) =>
+                {
+                    current = element.name().as_ref().to_vec();
+                    if current == b"trans-unit"
+                    {
+                        let id = element.try_get_attribute("id").ok().flatten()
+                            .and_then(|a| a.unescape_value().ok().map(|v| v.into_owned()))
+                            .ok_or_else(|| format!("trans-unit without id at position {}", reader.buffer_position()))?;
+                        translations.entries.push(StringEntry{key: id, source: String::new(), translation: String::new()});
+                    }
+                }
+                Ok(Event::Text(ref e)) =>
+                {
+                    let value = e.unescape().map_err(|e| format!("{:?}", e))?.into_owned();
+                    if let Some(entry) = translations.entries.last_mut()
+                    {
+                        match current.as_slice()
+                        {
+                            b"source" => entry.source = value,
+                            b"target" => entry.translation = value,
+                            _ => {}
+                        }
+                    }
+                }
+                Ok(Event::End(_)) => current.clear(),
+                _ => ()
+            }
+        }
+
+        Ok(translations)
+    }
+
+    /// Copies `source` to `filepath` with the translated strings written in. Strings without
+    /// a translation and everything else in the file stay as they were.
+    pub fn injectStrings(&self, source: &Path, filepath: &Path, table: &TableRule) -> Result<(), String>
+    {
+        let mut reader = Reader::from_file(source)
+            .map_err(|e| format!("Could not open file {}: {}", source.display(), e))?;
+        let readError = |position: usize, e: quick_xml::Error| format!("{}: Error at position {}: {:?}", source.display(), position, e);
+        let mut writer = Writer::new(Vec::new());
+        let file = baseName(source);
+        let translations = self.translations();
+
+        let mut buf = Vec::new();
+        let mut depth = 0;
+        // Events of the current record, held back until its index is known
+        let mut record: Vec<Event<'static>> = Vec::new();
+        let mut count = 0;
+        loop
+        {
+            let event = match reader.read_event_into(&mut buf)
+            {
+                Err(e) => return Err(readError(reader.buffer_position(), e)),
+                Ok(Event::Eof) => break,
+                Ok(event) => event
+            };
+
+            match event
+            {
+                Event::Start(ref element) if depth == 0 && element.name().as_ref() == table.record.as_bytes() => depth = 1,
+                Event::Start(_) if depth > 0 => depth += 1,
+                Event::End(_) if depth > 0 => depth -= 1,
+                _ if depth == 0 =>
+                {
+                    writer.write_event(event).map_err(|e| readError(reader.buffer_position(), e))?;
+                    buf.clear();
+                    continue;
+                }
+                _ => {}
+            }
+
+            record.push(event.into_owned());
+            if depth == 0
+            {
+                count += writeRecord(&mut writer, &record, &translations, &file, table)
+                    .map_err(|e| readError(reader.buffer_position(), e))?;
+                record.clear();
+            }
+            buf.clear();
+        }
+
+        println!("{} strings translated", count);
+        let mut file = createOutput(filepath)?;
+        file.write_all(&writer.into_inner())
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    // Translated strings by key, the first entry wins if a key is there twice
+    fn translations(&self) -> HashMap<&str, &str>
+    {
+        let mut translations = HashMap::new();
+        for entry in self.entries.iter().filter(|e| !e.translation.is_empty())
+        {
+            translations.entry(entry.key.as_str()).or_insert(entry.translation.as_str());
+        }
+        translations
+    }
+}
+
+
+// Writes the events of one record with its string fields translated. Returns the number of strings replaced.
+fn writeRecord(writer: &mut Writer<Vec<u8>>, record: &[Event], translations: &HashMap<&str, &str>, file: &str, table: &TableRule) -> quick_xml::Result<usize>
+{
+    let index = recordIndex(record, table).unwrap_or_default();
+    let mut count = 0;
+    let mut depth = 0;
+    // Translation of the field being written, its original text is left out
+    let mut translation: Option<&str> = None;
+    for event in record
+    {
+        match event
+        {
+            Event::Start(element) =>
+            {
+                depth += 1;
+                if depth == 2
+                {
+                    let field = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                    if table.strings.contains(&field)
+                    {
+                        translation = translations.get(key(file, &index, &field).as_str()).copied();
+                    }
+                }
+                writer.write_event(event)?;
+                if let Some(text) = translation.filter(|_| depth == 2)
+                {
+                    writer.write_event(Event::Text(BytesText::new(text)))?;
+                    count += 1;
+                }
+                continue;
+            }
+            Event::Empty(element) if depth == 1 =>
+            {
+                let field = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                let text = Some(&field).filter(|f| table.strings.contains(f))
+                    .and_then(|f| translations.get(key(file, &index, f).as_str()));
+                if let Some(text) = text
+                {
+                    writer.write_event(Event::Start(element.to_owned()))?;
+                    writer.write_event(Event::Text(BytesText::new(text)))?;
+                    writer.write_event(Event::End(element.to_end()))?;
+                    count += 1;
+                    continue;
+                }
+            }
+            Event::End(_) =>
+            {
+                if depth == 2 { translation = None; }
+                depth -= 1;
+            }
+            Event::Text(_) | Event::CData(_) if depth == 2 && translation.is_some() => continue,
+            _ => {}
+        }
+        writer.write_event(event)?;
+    }
+    Ok(count)
+}
+
+// Text of the record's index field
+fn recordIndex(record: &[Event], table: &TableRule) -> Option<String>
+{
+    let mut depth = 0;
+    let mut current = false;
+    for event in record
+    {
+        match event
+        {
+            Event::Start(element) =>
+            {
+                depth += 1;
+                current = depth == 2 && element.name().as_ref() == table.index.as_bytes();
+            }
+            Event::Text(e) if current => return e.unescape().ok().map(|text| text.trim().to_string()),
+            Event::End(_) => { depth -= 1; current = false; }
+            _ => {}
+        }
+    }
+    None
+}
+
+
+/// (index, field, text) of every string field in `filepath`
+pub fn readStrings(filepath: &Path, table: &TableRule) -> Result<Vec<(String, String, String)>, String>
+{
+    let mut strings = Vec::new();
+
+    let mut reader = Reader::from_file(filepath)
+        .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+    reader.trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut depth = 0;
+    let mut index = String::new();
+    let mut current = String::new();
+    let mut text = String::new();
+    // String fields of the current record, added once the record ends and its index is known
+    let mut fields: Vec<(String, String)> = Vec::new();
+    loop
+    {
+        match reader.read_event_into(&mut buf)
+        {
+            Err(e) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e)),
+            Ok(Event::Eof) => break,
+
+            Ok(Event::Start(ref element)) =>
+            {
+                if depth > 0 { depth += 1; }
+                else if element.name().as_ref() == table.record.as_bytes() { depth = 1; index.clear(); fields.clear(); }
+                current = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                text.clear();
+            }
+            Ok(Event::Empty(ref element)) if depth == 1 =>
+            {
+                let field = String::from_utf8_lossy(element.name().as_ref()).into_owned();
+                if table.strings.contains(&field) { fields.push((field, String::new())); }
+            }
+            Ok(Event::Text(ref e)) if depth == 2 =>
+            {
+                let value = e.unescape()
+                    .map_err(|e| format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), e))?;
+                text.push_str(&value);
+                if current == table.index { index = text.trim().to_string(); }
+            }
+            Ok(Event::End(_)) =>
+            {
+                if depth == 2 && table.strings.contains(&current)
+                {
+                    fields.push((current.clone(), text.clone()));
+                }
+                if depth == 1
+                {
+                    strings.extend(fields.drain(..).map(|(field, text)| (index.clone(), field, text)));
+                }
+                if depth > 0 { depth -= 1; }
+                current.clear();
+            }
+            _ => ()
+        }
+        buf.clear();
+    }
+
+    Ok(strings)
+}
+
+
+fn key(file: &str, index: &str, field: &str) -> String
+{
+    format!("{}/{}/{}", file, index, field)
+}
+
+// Localized files share the keys of their base file
+fn baseName(filepath: &Path) -> String
+{
+    let filename = filepath.file_name().unwrap().to_string_lossy();
+    splitLanguage(&filename).1.to_string()
+}
+
+/// Files named .xlf or .xliff are XLIFF, everything else PO
+pub fn isXliff(filepath: &Path) -> bool
+{
+    match filepath.extension().and_then(|e| e.to_str())
+    {
+        Some(extension) => extension.eq_ignore_ascii_case("xlf") || extension.eq_ignore_ascii_case("xliff"),
+        None => false
+    }
+}
+
+fn poEscape(value: &str) -> String
+{
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\t', "\\t")
+}
+
+fn poUnescape(quoted: &str) -> Option<String>
+{
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next()
+    {
+        if c != '\\' { value.push(c); continue; }
+        match chars.next()?
+        {
+            'n' => value.push('\n'),
+            't' => value.push('\t'),
+            other => value.push(other)
+        }
+    }
+    Some(value)
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+
+//-----------------------------------------------------------------------------
+// Watching a folder for changes
+//-----------------------------------------------------------------------------
+// Files are polled by their modification time, so it works the same on every platform and
+// network drives. Editors often save a file in several writes; a changed file is only reported
+// once it has not changed for DEBOUNCE.
+
+pub const INTERVAL: Duration = Duration::from_millis(250);
+pub const DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub struct Watcher
+{
+    dirpath: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+    /// Changed files and when they last changed
+    pending: HashMap<PathBuf, Instant>
+}
+impl Watcher
+{
+    /// Starts watching the files in `dirpath` for which `watched` is true. Existing files are
+    /// not reported until they change.
+    pub fn new(dirpath: &Path, watched: &dyn Fn(&Path) -> bool) -> Result<Watcher, String>
+    {
+        let mut watcher = Watcher{dirpath: dirpath.to_path_buf(), modified: HashMap::new(), pending: HashMap::new()};
+        watcher.modified = watcher.scan(watched)?;
+        Ok(watcher)
+    }
+
+    pub fn files(&self) -> usize
+    {
+        self.modified.len()
+    }
+
+    /// Files that changed and have settled since the last call, sorted by name
+    pub fn poll(&mut self, watched: &dyn Fn(&Path) -> bool) -> Result<Vec<PathBuf>, String>
+    {
+        let now = Instant::now();
+        let modified = self.scan(watched)?;
+        for (path, time) in &modified
+        {
+            if self.modified.get(path) != Some(time)
+            {
+                self.pending.insert(path.clone(), now);
+            }
+        }
+        self.modified = modified;
+
+        let mut settled: Vec<PathBuf> = self.pending.iter()
+            .filter(|(_, changed)| now.duration_since(**changed) >= DEBOUNCE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        settled.sort();
+        for path in &settled
+        {
+            self.pending.remove(path);
+        }
+        // Removed files are not reported
+        Ok(settled.into_iter().filter(|p| self.modified.contains_key(p)).collect())
+    }
+
+    fn scan(&self, watched: &dyn Fn(&Path) -> bool) -> Result<HashMap<PathBuf, SystemTime>, String>
+    {
+        let entries = std::fs::read_dir(&self.dirpath)
+            .map_err(|e| format!("Could not read directory {}: {}", self.dirpath.display(), e))?;
+        Ok(entries.filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file() && watched(p))
+            .filter_map(|p| std::fs::metadata(&p).and_then(|m| m.modified()).ok().map(|time| (p, time)))
+            .collect())
+    }
+}
@@ -0,0 +1,258 @@
+use std::path::Path;
+use std::io::{self, BufReader, Write};
+use std::fs::File;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::rules::TableRule;
+use crate::{createOutput, parseString, parsebool, parsei8, parseu8, parseu16};
+
+
+//-----------------------------------------------------------------------------
+// Field mapping
+//-----------------------------------------------------------------------------
+// Current names are the elements weaponStartElementHandle in Tactical/XML_Weapons.cpp of the 1.13 source
+// accepts, the old ones those of Weapons.xml files written for earlier builds of that parser.
+// Old field name -> current field name
+const RENAMED_FIELDS: &[(&str, &str)] = &[
+    ("silencedSound", "SilencedSound"),
+    ("sSilencedSound", "SilencedSound"),
+    ("bAutoFireShotsPerFiveAP", "bAutofireShotsPerFiveAP"),
+    ("ubAutoPenalty", "AutoPenalty"),
+    ("ubBurstAPs", "bBurstAP"),
+    ("usReloadAPs", "APsToReload"),
+];
+
+// Fields of old files that weaponStartElementHandle ignores and no WEAPONTYPE member (Tactical/Item Types.h)
+// stands for anymore, left out of the output
+const OBSOLETE_FIELDS: &[&str] = &["fSilenced", "ubSoundVolume"];
+
+
+pub struct Weapon
+{
+    pub index: u16,
+    pub name: String,
+    pub shotsPer4Turns: u8,
+    pub shotsPerBurst: u8,
+    pub burstPenalty: u8,
+    pub sound: u16,
+    pub burstSound: Option<u16>,
+    pub autofireShotsPerFiveAP: Option<i8>,
+    pub autoPenalty: Option<u8>,
+    pub noSemiAuto: Option<bool>,
+    // All fields with their current names in file order, as they are written out
+    pub fields: Vec<(String, String)>
+}
+impl Weapon
+{
+    pub fn new() -> Weapon
+    {
+        Weapon{
+            index: 0,
+            name: String::new(),
+            shotsPer4Turns: 0,
+            shotsPerBurst: 0,
+            burstPenalty: 0,
+            sound: 0,
+            burstSound: None,
+            autofireShotsPerFiveAP: None,
+            autoPenalty: None,
+            noSemiAuto: None,
+            fields: Vec::new()
+        }
+    }
+
+    /// Defaults for the fields introduced after the old format that this weapon is missing:
+    ///   bAutofireShotsPerFiveAP  0, no autofire
+    ///   AutoPenalty              ubBurstPenalty for autofire weapons, autofire used the burst penalty before
+    ///   NoSemiAuto               1 for autofire weapons without single shots (ubShotsPer4Turns 0), otherwise 0
+    ///   sBurstSound              sSound for weapons that can burst, they used the single shot sound before
+    pub fn derivedFields(&self) -> Vec<(String, String)>
+    {
+        let mut derived: Vec<(String, String)> = Vec::new();
+        let autofire = self.autofireShotsPerFiveAP.unwrap_or(0);
+
+        if self.autofireShotsPerFiveAP.is_none()
+        {
+            derived.push(("bAutofireShotsPerFiveAP".to_string(), "0".to_string()));
+        }
+        if self.autoPenalty.is_none()
+        {
+            let value = if autofire > 0 { self.burstPenalty } else { 0 };
+            derived.push(("AutoPenalty".to_string(), value.to_string()));
+        }
+        if self.noSemiAuto.is_none()
+        {
+            let value = autofire > 0 && self.shotsPer4Turns == 0;
+            derived.push(("NoSemiAuto".to_string(), (value as u8).to_string()));
+        }
+        if self.burstSound.is_none()
+        {
+            let value = if self.shotsPerBurst > 0 { self.sound } else { 0 };
+            derived.push(("sBurstSound".to_string(), value.to_string()));
+        }
+
+        derived
+    }
+}
+
+
+pub struct Weapons
+{
+    pub weapons: Vec<Weapon>,
+    // Obsolete fields that were dropped, with the number of weapons that had them
+    pub dropped: Vec<(String, u32)>,
+    // Fields given under both their old and current name, with the value that was kept
+    pub duplicates: Vec<String>
+}
+impl Weapons
+{
+    pub const FILE: &'static str = "Weapons.xml";
+
+    pub fn new() -> Weapons
+    {
+        let weapons = Vec::new();
+        let dropped = Vec::new();
+        let duplicates = Vec::new();
+
+        Weapons{weapons, dropped, duplicates}
+    }
+
+    pub fn loadWeapons(filepath: &Path, table: &TableRule) -> Result<Weapons, String>
+    {
+        let mut weapons = Weapons::new();
+
+        let mut reader = Reader::from_file(filepath)
+            .map_err(|e| format!("Could not open file {}: {}", filepath.display(), e))?;
+        reader.trim_text(true);
+        let mut buf = Vec::new();
+        loop
+        {
+            match reader.read_event_into(&mut buf)
+            {
+                Err(element) => return Err(format!("{}: Error at position {}: {:?}", filepath.display(), reader.buffer_position(), element)),
+                Ok(Event::Eof) => break,
+
+                Ok(Event::Start(ref element)) if element.name().as_ref() == table.record.as_bytes() =>
+                {
+                    weapons.readItem(&mut reader, &mut buf, table)
+                        .map_err(|e| format!("{}: {}", filepath.display(), e))?;
+                }
+                _ => ()
+            }
+            buf.clear();
+        }
+        Ok(weapons)
+    }
+
+
+    pub fn saveWeapons(&self, filepath: &Path, table: &TableRule) -> Result<(), String>
+    {
+        let mut writer = createOutput(filepath)?;
+        self.writeXml(&mut writer, table)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Could not write file {}: {}", filepath.display(), e))
+    }
+
+    fn writeXml<W: Write>(&self, writer: &mut W, table: &TableRule) -> io::Result<()>
+    {
+		writeln!(writer, "<{}>", table.root)?;
+
+        for weapon in &self.weapons
+        {
+	    	writeln!(writer, "\t<{}>", table.record)?;
+
+            for (tag, value) in weapon.fields.iter().chain(weapon.derivedFields().iter())
+            {
+                write_tag_s!(writer, value, tag, true)?;
+            }
+
+            writeln!(writer, "\t</{}>", table.record)?;
+        }
+
+		writeln!(writer, "</{}>", table.root)
+    }
+
+    /// Warnings about fields that were dropped because they have no modern equivalent, and fields
+    /// given under both their old and current name
+    pub fn warnings(&self) -> Vec<String>
+    {
+        self.dropped.iter()
+            .map(|(field, count)| format!("{} has no modern equivalent and was dropped from {} weapons", field, count))
+            .chain(self.duplicates.iter().cloned())
+            .collect()
+    }
+
+    pub fn readItem(&mut self, reader: &mut Reader<BufReader<File>>, buf: &mut Vec<u8>, table: &TableRule) -> Result<(), String>
+	{
+		let mut weapon = Weapon::new();
+		// Tags the fields were read from, and fields that were there a second time with their tag and value
+		let mut sources: Vec<String> = Vec::new();
+		let mut duplicates: Vec<(usize, String, String)> = Vec::new();
+		loop
+		{
+			match reader.read_event_into(buf)
+			{
+				    Ok(Event::Start(e)) =>
+				    {
+					        let tag = e.name().as_ref().to_vec();
+					        let oldName = String::from_utf8_lossy(&tag).into_owned();
+					        if OBSOLETE_FIELDS.contains(&oldName.as_str())
+					        {
+						        parseString(reader, buf, &tag)?;
+						        match self.dropped.iter_mut().find(|(f, _)| *f == oldName)
+						        {
+							        Some((_, count)) => *count += 1,
+							        None => self.dropped.push((oldName, 1))
+						        }
+						        buf.clear();
+						        continue;
+					        }
+
+					        let name = match RENAMED_FIELDS.iter().find(|(old, _)| *old == oldName)
+					        {
+						        Some((_, new)) => new.to_string(),
+						        None => oldName.clone()
+					        };
+					        // Files that have both the old and the current name keep the first one
+					        if let Some(k) = weapon.fields.iter().position(|(f, _)| *f == name)
+					        {
+						        let value = parseString(reader, buf, &tag)?;
+						        duplicates.push((k, oldName, value));
+						        buf.clear();
+						        continue;
+					        }
+					        let value = match name.as_str()
+					        {
+			            		"uiIndex" => { weapon.index = parseu16(reader, buf, &oldName)?; weapon.index.to_string() }
+			            		"szWeaponName" => { weapon.name = parseString(reader, buf, &tag)?; weapon.name.clone() }
+			            		"ubShotsPer4Turns" => { weapon.shotsPer4Turns = parseu8(reader, buf, &oldName)?; weapon.shotsPer4Turns.to_string() }
+			            		"ubShotsPerBurst" => { weapon.shotsPerBurst = parseu8(reader, buf, &oldName)?; weapon.shotsPerBurst.to_string() }
+			            		"ubBurstPenalty" => { weapon.burstPenalty = parseu8(reader, buf, &oldName)?; weapon.burstPenalty.to_string() }
+			            		"sSound" => { weapon.sound = parseu16(reader, buf, &oldName)?; weapon.sound.to_string() }
+			            		"sBurstSound" => { let value = parseu16(reader, buf, &oldName)?; weapon.burstSound = Some(value); value.to_string() }
+			            		"bAutofireShotsPerFiveAP" => { let value = parsei8(reader, buf, &oldName)?; weapon.autofireShotsPerFiveAP = Some(value); value.to_string() }
+			            		"AutoPenalty" => { let value = parseu8(reader, buf, &oldName)?; weapon.autoPenalty = Some(value); value.to_string() }
+			            		"NoSemiAuto" => { let value = parsebool(reader, buf, &oldName)?; weapon.noSemiAuto = Some(value); (value as u8).to_string() }
+								_ => parseString(reader, buf, &tag)?
+						        };
+					        weapon.fields.push((name, value));
+					        sources.push(oldName);
+				    }
+
+				    Err(e) => return Err(format!("Error at position {}: {:?}", reader.buffer_position(), e)),
+				    Ok(Event::Eof) => return Err(format!("Unclosed {} at end of file", table.record)),
+				    Ok(Event::End(ref element)) if element.name().as_ref() == table.record.as_bytes() => break,
+				    _ => (),
+			}
+			buf.clear();
+		}
+		for (k, tag, value) in duplicates
+		{
+			self.duplicates.push(format!("weapon {}: {} '{}' was kept, {} '{}' was dropped as it is the same field",
+				weapon.index, sources[k], weapon.fields[k].1, tag, value));
+		}
+		self.weapons.push(weapon);
+		Ok(())
+	}
+}
@@ -0,0 +1,82 @@
+#![allow(non_snake_case)]
+
+use JA2_ConvertXMLData::analysis::Report;
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+
+
+// Ira likes Fox, who dislikes Ira and Ivan, Ivan likes Fox. Only Gus has an opinion about Gus.
+fn mercOpinions() -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = vec![1, 2, 3, 4];
+    mercOpinions.nicknames = ["Ira", "Fox", "Ivan", "Gus"].iter().map(|n| n.to_string()).collect();
+    mercOpinions.opinions = vec![vec![0; 5]; 4];
+    mercOpinions.opinions[0][2] = 10;
+    mercOpinions.opinions[0][1] = 3;
+    mercOpinions.opinions[1][1] = -4;
+    mercOpinions.opinions[1][3] = -2;
+    mercOpinions.opinions[2][2] = 5;
+    mercOpinions.opinions[3][4] = -1;
+    mercOpinions
+}
+
+#[test]
+fn opinionsAreAnalyzed()
+{
+    let report = Report::analyze(&mercOpinions());
+
+    assert_eq!(report.asymmetric.iter().map(|a| (a.merc.zNickname.as_str(), a.target.zNickname.as_str(), a.modifier, a.reverse)).collect::<Vec<_>>(),
+        [("Ira", "Fox", 10, -4), ("Ivan", "Fox", 5, -2)]);
+    assert_eq!(report.selfOpinions.iter().map(|s| (s.merc.zNickname.as_str(), s.modifier)).collect::<Vec<_>>(),
+        [("Ira", 3), ("Gus", -1)]);
+    assert_eq!(report.mostLoved.iter().map(|s| (s.merc.zNickname.as_str(), s.total)).collect::<Vec<_>>(), [("Fox", 15)]);
+    assert_eq!(report.mostLoved[0].opinions, 2);
+    assert_eq!(report.mostHated.iter().map(|s| (s.merc.zNickname.as_str(), s.total)).collect::<Vec<_>>(),
+        [("Ira", -4), ("Ivan", -2)]);
+    assert_eq!(report.histogram.iter().map(|b| (b.modifier, b.count)).collect::<Vec<_>>(),
+        [(-4, 1), (-2, 1), (-1, 1), (3, 1), (5, 1), (10, 1)]);
+}
+
+#[test]
+fn mercsWithOnlySelfOpinionsAreIgnored()
+{
+    let report = Report::analyze(&mercOpinions());
+    assert_eq!(report.ignored.iter().map(|m| (m.uiIndex, m.zNickname.as_str())).collect::<Vec<_>>(), [(4, "Gus")]);
+
+    let mut mercOpinions = mercOpinions();
+    mercOpinions.opinions[0][4] = 1;
+    assert!(Report::analyze(&mercOpinions).ignored.is_empty());
+}
+
+#[test]
+fn theReportIsWrittenAsText()
+{
+    let bar = "#".repeat(40);
+    let text = Report::analyze(&mercOpinions()).toText();
+
+    assert_eq!(text, format!("Asymmetric relationships: 2\n\
+        \tIra likes Fox (+10), Fox dislikes Ira (-4)\n\
+        \tIvan likes Fox (+5), Fox dislikes Ivan (-2)\n\
+        \n\
+        Mercs no one has an opinion about: 1\n\
+        \tGus (4)\n\
+        \n\
+        Self-opinions: 2\n\
+        \tIra (1): +3\n\
+        \tGus (4): -1\n\
+        \n\
+        Most loved:\n\
+        \t  +15  Fox (2 opinions)\n\
+        \n\
+        Most hated:\n\
+        \t   -4  Ira (1 opinions)\n\
+        \t   -2  Ivan (1 opinions)\n\
+        \n\
+        Modifiers:\n\
+        \t  -4      1  {bar}\n\
+        \t  -2      1  {bar}\n\
+        \t  -1      1  {bar}\n\
+        \t  +3      1  {bar}\n\
+        \t  +5      1  {bar}\n\
+        \t +10      1  {bar}\n"));
+}
@@ -0,0 +1,50 @@
+#![allow(dead_code)]
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Directory for the files of one test, removed with everything in it when dropped. Tests run in
+/// parallel, so every directory gets its own number.
+pub struct TempDir
+{
+    path: PathBuf
+}
+impl TempDir
+{
+    pub fn new(name: &str) -> TempDir
+    {
+        let n = COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("ja2-{}-{}-{}", name, std::process::id(), n));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir{path}
+    }
+
+    /// Writes `contents` to the file `name` in the directory
+    pub fn file(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf
+    {
+        let filepath = self.path.join(name);
+        std::fs::write(&filepath, contents).unwrap();
+        filepath
+    }
+}
+impl Deref for TempDir
+{
+    type Target = Path;
+
+    fn deref(&self) -> &Path
+    {
+        &self.path
+    }
+}
+impl Drop for TempDir
+{
+    fn drop(&mut self)
+    {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,163 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use quick_xml::Reader;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+use common::TempDir;
+
+
+fn convert(file: &str, xml: &str) -> String
+{
+    let rules = RuleSet::builtin();
+    let table = rules.table(file).unwrap();
+    let mut output = Vec::new();
+    table.convertRecords(Reader::from_reader(xml.as_bytes()), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+
+#[test]
+fn opinionsAreRewritten()
+{
+    let xml = "<MERCOPINIONS><OPINION><uiIndex>3</uiIndex><Opinion0>0</Opinion0><Opinion7>-4</Opinion7></OPINION></MERCOPINIONS>";
+    let output = convert("MercOpinions.xml", xml);
+
+    assert!(output.contains("<uiIndex>3</uiIndex>"));
+    assert!(output.contains("<AnOpinion id = \"7\" modifier = \"-4\"/>"));
+    assert!(!output.contains("Opinion0"));
+}
+
+#[test]
+fn commentsArePassedThrough()
+{
+    let xml = "<MERCOPINIONS><!-- header --><OPINION><!-- Ira --><uiIndex>3</uiIndex></OPINION></MERCOPINIONS>";
+    let output = convert("MercOpinions.xml", xml);
+
+    assert!(output.contains("\t<!-- header -->\n"));
+    assert!(output.contains("\t\t<!-- Ira -->\n\t\t<uiIndex>3</uiIndex>"));
+}
+
+#[test]
+fn cdataIsKept()
+{
+    let xml = "<ITEMLIST><ITEM><uiIndex>1</uiIndex><szItemDesc><![CDATA[a < b & c]]></szItemDesc></ITEM></ITEMLIST>";
+    let output = convert("Items.xml", xml);
+
+    assert!(output.contains("<szItemDesc><![CDATA[a < b & c]]></szItemDesc>"));
+}
+
+#[test]
+fn attributesArePassedThrough()
+{
+    let xml = "<ITEMLIST><ITEM id=\"1\"><uiIndex>1</uiIndex><Foo a=\"1\" b=\"2\"/><Bar c=\"x &amp; y\"><Baz d=\"3\">4</Baz></Bar></ITEM></ITEMLIST>";
+    let output = convert("Items.xml", xml);
+
+    assert!(output.contains("\t<ITEM id=\"1\">\n"), "{}", output);
+    assert!(output.contains("<Foo a=\"1\" b=\"2\"/>"), "{}", output);
+    assert!(output.contains("<Bar c=\"x &amp; y\">\n"), "{}", output);
+    assert!(output.contains("<Baz d=\"3\">4</Baz>"), "{}", output);
+    assert!(output.contains("</Bar>"), "{}", output);
+}
+
+#[test]
+fn opinionTargetsAboveTheGameLimitAreRejected()
+{
+    let dirpath = TempDir::new("convert");
+    let filepath = dirpath.file("MercOpinions.xml", "<MERCOPINIONS><OPINION><uiIndex>3</uiIndex><Opinion4000000000>5</Opinion4000000000></OPINION></MERCOPINIONS>");
+    let rules = RuleSet::builtin();
+    let table = rules.table("MercOpinions.xml").unwrap();
+
+    let result = MercOpinions::loadMercOpinions(&filepath, table);
+    assert!(result.is_err_and(|e| e.contains("4000000000")));
+}
+
+#[test]
+fn symbolicTargetsFollowTheRuleTemplate()
+{
+    let dirpath = TempDir::new("convert");
+    let rulespath = dirpath.file("rules.toml", "[[table]]\nfile = \"MercOpinions.xml\"\nroot = \"MERCOPINIONS\"\nrecord = \"OPINION\"\nsymbolic = true\n\n\
+        [[table.rule]]\nsource = \"Opinion{n}\"\ntarget = 'Feeling about = \"{n}\" value = \"{value}\"'\nskip = \"0\"\n");
+    let rules = RuleSet::load(&rulespath).unwrap();
+    let table = rules.table("MercOpinions.xml").unwrap();
+
+    let source = dirpath.file("Symbolic.xml", "<MERCOPINIONS>\
+        <OPINION><uiIndex>1</uiIndex><zNickname>Ira</zNickname><Opinion2>5</Opinion2><Opinion9>-1</Opinion9></OPINION>\
+        <OPINION><uiIndex>2</uiIndex><zNickname>Fox</zNickname><Opinion1>-3</Opinion1></OPINION></MERCOPINIONS>");
+    let mercOpinions = MercOpinions::loadMercOpinions(&source, table).unwrap();
+    let output = source.with_file_name("Symbolic out.xml");
+    mercOpinions.saveMercOpinions(&output, table).unwrap();
+
+    let text = std::fs::read_to_string(&output).unwrap();
+    assert!(text.contains("<Feeling target = \"Fox\" value = \"5\"/>"), "{}", text);
+    assert!(text.contains("<Feeling about = \"9\" value = \"-1\"/>"), "{}", text);
+    assert!(text.contains("<Feeling target = \"Ira\" value = \"-3\"/>"), "{}", text);
+
+    let reloaded = MercOpinions::loadMercOpinions(&output, table).unwrap();
+    assert_eq!(reloaded.opinions[0][2], 5);
+    assert_eq!(reloaded.opinions[0][9], -1);
+    assert_eq!(reloaded.opinions[1][1], -3);
+}
+
+#[test]
+fn unknownSymbolicTargetsSuggestNicknames()
+{
+    let dirpath = TempDir::new("convert");
+    let rules = RuleSet::builtin();
+    let table = rules.table("MercOpinions.xml").unwrap();
+
+    let opinion = |uiIndex: u8, nickname: &str, target: &str|
+        format!("<OPINION><uiIndex>{}</uiIndex><zNickname>{}</zNickname><AnOpinion target = \"{}\" modifier = \"5\"/></OPINION>", uiIndex, nickname, target);
+    let source = dirpath.file("MercOpinions.xml", format!("<MERCOPINIONS>{}{}{}{}</MERCOPINIONS>",
+        opinion(1, "Ira", "foxx"), opinion(2, "Fox", "Iva"), opinion(3, "Ivan", "Shadow"), opinion(4, "Ivanov", "ira")));
+
+    let errors = MercOpinions::loadMercOpinions(&source, table).err().unwrap();
+    let errors: Vec<&str> = errors.lines().map(|e| e.split_once(": ").unwrap().1).collect();
+    assert_eq!(errors, [
+        "Ira (1): Unknown target foxx, did you mean Fox?",
+        "Fox (2): Unknown target Iva, did you mean Ira or Ivan or Ivanov?",
+        "Ivan (3): Unknown target Shadow"
+    ]);
+}
+
+#[test]
+fn brokenSourcesLeaveNoOutput()
+{
+    let dirpath = TempDir::new("convert");
+    let source = dirpath.file("Broken.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEMS></ITEMLIST>");
+    let output = source.with_file_name("Broken out.xml");
+    let rules = RuleSet::builtin();
+    let table = rules.table("Items.xml").unwrap();
+
+    let result = table.convert(&source, &output);
+    assert!(result.is_err_and(|e| e.contains("Broken.xml")));
+    assert!(!output.exists());
+}
+
+#[test]
+fn tagsThatAreNotUtf8AreRead()
+{
+    let dirpath = TempDir::new("convert");
+    let source = dirpath.file("MercOpinions.xml", b"<MERCOPINIONS><OPINION><uiIndex>1</uiIndex><Fi\xe9ld>1</Fi\xe9ld><Opinion2>5</Opinion2></OPINION></MERCOPINIONS>");
+    let rules = RuleSet::builtin();
+    let table = rules.table("MercOpinions.xml").unwrap();
+
+    let mercOpinions = MercOpinions::loadMercOpinions(&source, table).unwrap();
+    assert_eq!(mercOpinions.opinions[0][2], 5);
+    assert!(table.convert(&source, &source.with_file_name("MercOpinions out.xml")).is_ok());
+}
+
+#[test]
+fn opinionTablesWithoutARuleAreAnError()
+{
+    let dirpath = TempDir::new("convert");
+    let rulespath = dirpath.file("rules.toml", "[[table]]\nfile = \"MercOpinions.xml\"\nroot = \"MERCOPINIONS\"\nrecord = \"OPINION\"\n");
+    let rules = RuleSet::load(&rulespath).unwrap();
+    let table = rules.table("MercOpinions.xml").unwrap();
+
+    let output = dirpath.join("MercOpinions out.xml");
+    let result = MercOpinions::new().saveMercOpinions(&output, table);
+    assert!(result.is_err_and(|e| e.contains("no opinion rule")));
+}
@@ -0,0 +1,99 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::csv;
+use common::TempDir;
+
+
+const ITEMS: &str = "<ITEMLIST>
+	<ITEM>
+		<uiIndex>1</uiIndex>
+		<szItemName>Knife &amp; Fork &lt;old&gt;</szItemName>
+		<szLongItemName></szLongItemName>
+		<DefaultAttachment>5</DefaultAttachment>
+		<DefaultAttachment>7</DefaultAttachment>
+		<STAND_MODIFIERS>
+			<FlatBase>2</FlatBase>
+			<PercentBase>-10</PercentBase>
+		</STAND_MODIFIERS>
+	</ITEM>
+	<ITEM>
+		<uiIndex>2</uiIndex>
+		<szItemName>\"Spoon\", large</szItemName>
+		<szLongItemName>Spoon</szLongItemName>
+		<DefaultAttachment>9</DefaultAttachment>
+		<STAND_MODIFIERS>
+			<FlatBase>1</FlatBase>
+		</STAND_MODIFIERS>
+	</ITEM>
+</ITEMLIST>
+";
+
+#[test]
+fn tableRoundTripsThroughCsv()
+{
+    let dirpath = TempDir::new("csv");
+    let source = dirpath.join("Items.xml");
+    std::fs::write(&source, ITEMS).unwrap();
+
+    let rules = RuleSet::builtin();
+    let table = rules.table("Items.xml").unwrap();
+    let rows = csv::tableToRows(&source, table).unwrap();
+    assert_eq!(rows[0], ["uiIndex", "szItemName", "szLongItemName", "DefaultAttachment", "DefaultAttachment#2",
+        "STAND_MODIFIERS/FlatBase", "STAND_MODIFIERS/PercentBase"]);
+    assert_eq!(rows[1], ["1", "Knife & Fork <old>", "", "5", "7", "2", "-10"]);
+
+    let csvpath = dirpath.join("Items.csv");
+    csv::saveCsv(&rows, &csvpath).unwrap();
+    let imported = csv::loadCsv(&csvpath).unwrap();
+    assert_eq!(imported, rows);
+
+    let xml = String::from_utf8(csv::rowsToXml(&imported, table)).unwrap();
+    assert_eq!(xml, ITEMS);
+}
+
+#[test]
+fn opinionsRoundTripThroughCsv()
+{
+    let rows = vec![
+        vec!["uiIndex".to_string(), "zNickname".to_string(), "Barry".to_string(), "Blood".to_string()],
+        vec!["0".to_string(), "Barry".to_string(), "0".to_string(), "-4".to_string()],
+        vec!["1".to_string(), "Blood".to_string(), "12".to_string(), "0".to_string()]
+    ];
+    let mercOpinions = csv::opinionsFromRows(&rows).unwrap();
+    let exported = csv::opinionsToRows(&mercOpinions);
+
+    assert_eq!(exported[1][..4], rows[1][..]);
+    assert_eq!(exported[2][..4], rows[2][..]);
+    assert_eq!(csv::opinionsFromRows(&exported).unwrap().opinions[1][0], 12);
+}
+
+#[test]
+fn opinionColumnsPastTheLastMercAreRejected()
+{
+    let mut header = vec!["uiIndex".to_string(), "zNickname".to_string()];
+    header.extend((0..300).map(|j| j.to_string()));
+    let mut row = vec!["0".to_string(), "Barry".to_string()];
+    row.extend((0..300).map(|j| if j == 299 { "3".to_string() } else { String::new() }));
+
+    let result = csv::opinionsFromRows(&[header.clone(), row]);
+    assert!(result.is_err_and(|e| e.contains("Row 2, column 302") && e.contains("299")));
+
+    let mut empty = vec!["1".to_string(), "Blood".to_string()];
+    empty.extend((0..300).map(|_| String::new()));
+    assert!(csv::opinionsFromRows(&[header, empty]).is_ok());
+}
+
+#[test]
+fn brokenTablesAreAnError()
+{
+    let dirpath = TempDir::new("csv");
+    let source = dirpath.file("Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEMS></ITEMLIST>");
+    let rules = RuleSet::builtin();
+    let table = rules.table("Items.xml").unwrap();
+
+    assert!(csv::tableToRows(&source, table).is_err_and(|e| e.contains("Items.xml")));
+    assert!(csv::tableToRows(&dirpath.join("Missing.xml"), table).is_err_and(|e| e.contains("Could not open file")));
+}
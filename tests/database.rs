@@ -0,0 +1,39 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::database::Database;
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+use common::TempDir;
+
+
+fn mercOpinions(index: Vec<u8>) -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.nicknames = index.iter().map(|i| format!("Merc{}", i)).collect();
+    mercOpinions.opinions = index.iter().map(|i| vec![0, *i as i32]).collect();
+    mercOpinions.index = index;
+    mercOpinions
+}
+
+#[test]
+fn opinionsRoundTripThroughTheDatabase()
+{
+    let dirpath = TempDir::new("database");
+    let mut database = Database::create(&dirpath.join("RoundTrip.sqlite")).unwrap();
+    database.writeMercOpinions(&mercOpinions(vec![2, 5])).unwrap();
+
+    let restored = database.readMercOpinions().unwrap();
+    assert_eq!(restored.index, [2, 5]);
+    assert_eq!(restored.opinions, [vec![0, 2], vec![0, 5]]);
+}
+
+#[test]
+fn duplicateIndicesAreReportedWithTheirRecords()
+{
+    let dirpath = TempDir::new("database");
+    let mut database = Database::create(&dirpath.join("Duplicate.sqlite")).unwrap();
+
+    let result = database.writeMercOpinions(&mercOpinions(vec![2, 5, 2]));
+    assert!(result.is_err_and(|e| e.starts_with("Record 3 (Merc2) has uiIndex 2, which record 1 (Merc2) has already")));
+}
@@ -0,0 +1,53 @@
+#![allow(non_snake_case)]
+
+use JA2_ConvertXMLData::diff::OpinionDiff;
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+
+
+fn mercOpinions(mercs: &[(u8, &str)]) -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = mercs.iter().map(|(i, _)| *i).collect();
+    mercOpinions.nicknames = mercs.iter().map(|(_, n)| n.to_string()).collect();
+    mercOpinions.opinions = vec![vec![0; 5]; mercs.len()];
+    mercOpinions
+}
+
+
+#[test]
+fn addedRemovedAndChangedRecordsAreListed()
+{
+    // Fox is removed, Gus added and Ivan renamed
+    let mut old = mercOpinions(&[(1, "Ira"), (2, "Fox"), (3, "Ivan")]);
+    old.opinions[0][2] = 5;
+    let mut new = mercOpinions(&[(1, "Ira"), (3, "Ivan the Terrible"), (4, "Gus")]);
+    new.opinions[0][3] = -3;
+    new.opinions[1][4] = 2;
+
+    let diff = OpinionDiff::diffMercOpinions(&old, &new);
+    assert_eq!(diff.added, [4]);
+    assert_eq!(diff.removed, [2]);
+    assert_eq!(diff.toText(&old, &new), [
+        "Removed Fox (2)",
+        "Added Gus (4)",
+        "Nickname of 3: Ivan -> Ivan the Terrible",
+        "Ira -> Fox: +5 -> 0",
+        "Ira -> Ivan the Terrible: 0 -> -3",
+        "Ivan the Terrible -> Gus: 0 -> +2"
+    ]);
+}
+
+#[test]
+fn recordsAreMatchedByIndex()
+{
+    let mut old = mercOpinions(&[(1, "Ira"), (2, "Fox")]);
+    old.opinions[1][1] = 7;
+    let mut new = mercOpinions(&[(2, "Fox"), (1, "Ira")]);
+    new.opinions[0][1] = 7;
+    // Missing opinions are 0
+    new.opinions[1].truncate(2);
+
+    let diff = OpinionDiff::diffMercOpinions(&old, &new);
+    assert!(diff.isEmpty());
+    assert!(diff.toText(&old, &new).is_empty());
+}
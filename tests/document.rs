@@ -0,0 +1,117 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::document::{self, Document, Format};
+use JA2_ConvertXMLData::mercopinions::{MercOpinions, MercOpinionsDocument, OpinionRecord, AnOpinion};
+use common::TempDir;
+
+
+const ITEMS: &str = "<ITEMLIST>
+	<ITEM>
+		<uiIndex>1</uiIndex>
+		<szItemName>Knife &amp; Fork &lt;old&gt;</szItemName>
+		<STAND_MODIFIERS>
+			<FlatBase>2</FlatBase>
+			<CROUCH>
+				<PercentBase>-10</PercentBase>
+			</CROUCH>
+		</STAND_MODIFIERS>
+		<ItemCapacity size = \"1\" amount = \"4\"/>
+		<ItemCapacity size = \"2\" amount = \"3\"/>
+	</ITEM>
+</ITEMLIST>
+";
+
+#[test]
+fn tableRoundTripsThroughJsonAndYaml()
+{
+    let dirpath = TempDir::new("document");
+    let source = dirpath.join("Items.xml");
+    std::fs::write(&source, ITEMS).unwrap();
+
+    let rules = RuleSet::builtin();
+    let table = rules.table("Items.xml").unwrap();
+    for format in [Format::Json, Format::Yaml]
+    {
+        let documentpath = dirpath.join(format!("Items.{}", format.extension()));
+        document::save(&Document::loadXml(&source, table).unwrap(), &documentpath, format).unwrap();
+
+        let loaded: Document = document::load(&documentpath).unwrap();
+        let output = dirpath.join("Items out.xml");
+        loaded.saveXml(&output, table).unwrap();
+        assert_eq!(std::fs::read_to_string(output).unwrap(), ITEMS);
+    }
+
+    let json = std::fs::read_to_string(dirpath.join("Items.json")).unwrap();
+    assert!(json.contains("\"STAND_MODIFIERS\": {"));
+}
+
+#[test]
+fn opinionsRoundTripThroughDocuments()
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = vec![3, 7];
+    mercOpinions.nicknames = vec!["Fox".to_string(), "Ivan".to_string()];
+    mercOpinions.opinions = vec![vec![0, 0, 0, 0, 0, 0, 0, -5], vec![0, 0, 0, 12]];
+
+    let restored = MercOpinions::fromDocument(&mercOpinions.toDocument()).unwrap();
+    assert_eq!(restored.index, mercOpinions.index);
+    assert_eq!(restored.nicknames, mercOpinions.nicknames);
+    assert_eq!(restored.opinions, mercOpinions.opinions);
+}
+
+#[test]
+fn documentOpinionIdsAboveTheGameLimitAreAnError()
+{
+    let document = MercOpinionsDocument{
+        table: MercOpinions::FILE.to_string(),
+        records: vec![OpinionRecord{uiIndex: 1, zNickname: String::new(), opinions: vec![AnOpinion{id: 4000000000, modifier: 1}]}]
+    };
+    assert!(MercOpinions::fromDocument(&document).is_err_and(|e| e.contains("4000000000")));
+}
+
+#[test]
+fn attributesRoundTripThroughJsonAndYaml()
+{
+    let xml = "<ITEMLIST>
+	<ITEM id = \"7\">
+		<uiIndex>1</uiIndex>
+		<szItemName lang = \"en\">Knife</szItemName>
+		<STAND_MODIFIERS kind = \"flat\">
+			<FlatBase>2</FlatBase>
+		</STAND_MODIFIERS>
+	</ITEM>
+</ITEMLIST>
+";
+    let dirpath = TempDir::new("document");
+    let source = dirpath.file("Items.xml", xml);
+
+    let rules = RuleSet::builtin();
+    let table = rules.table("Items.xml").unwrap();
+    for format in [Format::Json, Format::Yaml]
+    {
+        let documentpath = dirpath.join(format!("Items.{}", format.extension()));
+        document::save(&Document::loadXml(&source, table).unwrap(), &documentpath, format).unwrap();
+
+        let loaded: Document = document::load(&documentpath).unwrap();
+        let output = dirpath.join("Items out.xml");
+        loaded.saveXml(&output, table).unwrap();
+        assert_eq!(std::fs::read_to_string(output).unwrap(), xml);
+    }
+
+    let json = std::fs::read_to_string(dirpath.join("Items.json")).unwrap();
+    assert!(json.contains("\"@lang\": \"en\""), "{}", json);
+    assert!(json.contains("\"#text\": \"Knife\""), "{}", json);
+}
+
+#[test]
+fn documentsAreNotWrittenAsXml()
+{
+    let dirpath = TempDir::new("document");
+    let output = dirpath.join("Items.xml");
+    let document = Document{table: "Items.xml".to_string(), records: Vec::new()};
+    assert!(document::save(&document, &output, Format::Xml).is_err());
+    assert!(!output.exists());
+}
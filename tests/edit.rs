@@ -0,0 +1,80 @@
+#![allow(non_snake_case)]
+
+use JA2_ConvertXMLData::edit::{parseScript, applyEdits, Operation, Selector};
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+
+
+fn mercOpinions() -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = vec![1, 2, 3];
+    mercOpinions.nicknames = vec!["Ira".to_string(), "Big Fox".to_string(), "Ivan".to_string()];
+    mercOpinions.opinions = vec![vec![0; 4], vec![0; 4], vec![0; 4]];
+    mercOpinions
+}
+
+
+#[test]
+fn scriptsAreParsed()
+{
+    let script = "# comment\nset \"Big Fox\",Ira -> Ivan 5; scale * -> * 0.5\n\nmirror 3  # trailing comment\nadd aim->3 -2";
+    let edits = parseScript(script, &mercOpinions(), &(0..=39)).unwrap();
+
+    assert_eq!(edits.iter().map(|e| e.line).collect::<Vec<_>>(), [2, 2, 4, 5]);
+    assert!(matches!(edits[0].operation, Operation::Set(5)));
+    assert!(matches!(&edits[0].mercs, Selector::Mercs(mercs) if mercs == &[2, 1]));
+    assert!(matches!(&edits[0].targets, Selector::Mercs(mercs) if mercs == &[3]));
+    assert!(matches!(edits[1].operation, Operation::Scale(f) if f == 0.5));
+    assert!(matches!((&edits[1].mercs, &edits[1].targets), (Selector::All, Selector::All)));
+    assert!(matches!((edits[2].operation, &edits[2].targets), (Operation::Mirror, Selector::All)));
+    assert!(matches!((edits[3].operation, &edits[3].mercs), (Operation::Add(-2), Selector::Aim(aim)) if aim == &(0..=39)));
+}
+
+#[test]
+fn scriptErrorsNameTheLine()
+{
+    let mercOpinions = mercOpinions();
+    for (script, error) in [
+        ("set Ira -> Ivan 5\nset Ira Ivan 5", "Line 2: set needs -> and the target mercs"),
+        ("add Ira -> Ivan x", "Line 1: Invalid modifier x"),
+        ("\nscale Ira -> Ivan", "Line 2: scale needs one value after the target mercs"),
+        ("mirror Ira -> Ivan 1", "Line 1: mirror takes no value"),
+        ("swap Ira -> Ivan 1", "Line 1: Unknown edit swap"),
+        ("set Nobody -> Ivan 1", "Line 1: ")
+    ]
+    {
+        match parseScript(script, &mercOpinions, &(0..=39))
+        {
+            Ok(_) => panic!("{} was accepted", script),
+            Err(e) => assert!(e.starts_with(error), "{}: {}", script, e)
+        }
+    }
+}
+
+#[test]
+fn resultsOutsideTheGameRangeAreRejected()
+{
+    for script in ["set Ira -> Ivan 128", "add Ira -> Ivan 100\nadd Ira -> Ivan 100", "add Ira -> Ivan 2147483647\nadd Ira -> Ivan 1", "set Ira -> Ivan -100\nscale Ira -> Ivan 2"]
+    {
+        let mut mercOpinions = mercOpinions();
+        let edits = parseScript(script, &mercOpinions, &(0..=39)).unwrap();
+        let result = applyEdits(&mut mercOpinions, &edits);
+        assert!(result.as_ref().is_err_and(|e| e.contains("opinion of Ira about Ivan") && e.contains("outside -128 to 127")), "{}: {:?}", script, result);
+    }
+
+    let mut mercOpinions = mercOpinions();
+    let edits = parseScript("set Ira -> Ivan 127; set Ira -> Ivan -128", &mercOpinions, &(0..=39)).unwrap();
+    assert_eq!(applyEdits(&mut mercOpinions, &edits), Ok(2));
+    assert_eq!(mercOpinions.opinions[0][3], -128);
+}
+
+#[test]
+fn aimSelectsTheMercsOfTheGivenRange()
+{
+    let mut mercOpinions = mercOpinions();
+    let edits = parseScript("set aim -> aim 7", &mercOpinions, &(2..=3)).unwrap();
+    assert_eq!(applyEdits(&mut mercOpinions, &edits), Ok(2));
+    assert_eq!(mercOpinions.opinions[0], [0, 0, 0, 0]);
+    assert_eq!(mercOpinions.opinions[1], [0, 0, 0, 7]);
+    assert_eq!(mercOpinions.opinions[2], [0, 0, 7, 0]);
+}
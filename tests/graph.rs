@@ -0,0 +1,90 @@
+#![allow(non_snake_case)]
+
+use JA2_ConvertXMLData::graph::{opinionsToDot, saveDot, GraphFilter};
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+
+mod common;
+use common::TempDir;
+
+
+// Ira (1) likes Fox (2) with 10 and dislikes the merc 45 with -5, Fox dislikes Ira with -1
+fn mercOpinions() -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = vec![1, 2, 45];
+    mercOpinions.nicknames = vec!["Ira".to_string(), "Big \"Fox\"".to_string(), String::new()];
+    mercOpinions.opinions = vec![vec![0; 46]; 3];
+    mercOpinions.opinions[0][2] = 10;
+    mercOpinions.opinions[0][45] = -5;
+    mercOpinions.opinions[1][1] = -1;
+    mercOpinions
+}
+
+fn edges(dot: &str) -> Vec<String>
+{
+    dot.lines().filter(|line| line.contains("->"))
+        .map(|line| line.trim().split(" [").next().unwrap().to_string())
+        .collect()
+}
+
+
+#[test]
+fn opinionsAreWrittenAsDot()
+{
+    let dot = opinionsToDot(&mercOpinions(), &GraphFilter::new());
+
+    assert_eq!(dot, "digraph MercOpinions {\n\
+        \tnode [shape = box, style = rounded];\n\
+        \tm1 [label = \"Ira\"];\n\
+        \tm2 [label = \"Big \\\"Fox\\\"\"];\n\
+        \tm45 [label = \"45\"];\n\
+        \tm1 -> m2 [label = \"10\", color = \"0.333 1.000 0.600\", penwidth = 3.0];\n\
+        \tm1 -> m45 [label = \"-5\", color = \"0.000 0.625 0.750\", penwidth = 2.0];\n\
+        \tm2 -> m1 [label = \"-1\", color = \"0.000 0.325 0.870\", penwidth = 1.2];\n\
+        }\n");
+}
+
+#[test]
+fn theFilterSelectsTheOpinionsDrawn()
+{
+    let mercOpinions = mercOpinions();
+
+    let mut filter = GraphFilter::new();
+    filter.minModifier = 5;
+    assert_eq!(edges(&opinionsToDot(&mercOpinions, &filter)), ["m1 -> m2", "m1 -> m45"]);
+
+    let mut filter = GraphFilter::new();
+    filter.aim = Some(0..=39);
+    assert_eq!(edges(&opinionsToDot(&mercOpinions, &filter)), ["m1 -> m2", "m2 -> m1"]);
+
+    filter.aim = Some(2..=45);
+    assert_eq!(edges(&opinionsToDot(&mercOpinions, &filter)), Vec::<String>::new());
+
+    let mut filter = GraphFilter::new();
+    filter.merc = Some(45);
+    let dot = opinionsToDot(&mercOpinions, &filter);
+    assert_eq!(edges(&dot), ["m1 -> m45"]);
+    assert!(!dot.contains("m2 ["));
+
+    // A merc without opinions still gets a node
+    let mut filter = GraphFilter::new();
+    filter.minModifier = 20;
+    filter.merc = Some(2);
+    let dot = opinionsToDot(&mercOpinions, &filter);
+    assert!(edges(&dot).is_empty());
+    assert!(dot.contains("\tm2 [label = \"Big \\\"Fox\\\"\"];\n"));
+}
+
+#[test]
+fn dotFilesAreSaved()
+{
+    let dirpath = TempDir::new("graph");
+    let dot = opinionsToDot(&mercOpinions(), &GraphFilter::new());
+
+    let filepath = dirpath.join("Graphs").join("MercOpinions.dot");
+    saveDot(&dot, &filepath).unwrap();
+    assert_eq!(std::fs::read_to_string(&filepath).unwrap(), dot);
+
+    let notADirectory = dirpath.file("MercOpinions.xml", "");
+    assert!(saveDot(&dot, &notADirectory.join("MercOpinions.dot")).is_err());
+}
@@ -0,0 +1,54 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::inventory::NPCInventory;
+use common::TempDir;
+
+
+#[test]
+fn droppedFieldsAreReported()
+{
+    let dirpath = TempDir::new("inventory");
+    let source = dirpath.join("TonyInventory.xml");
+    std::fs::write(&source, "<INVENTORYLIST>\
+        <INVENTORY><ubItemIndex>201</ubItemIndex><ubOptimalNumber>2</ubOptimalNumber><ubQuality>0</ubQuality></INVENTORY>\
+        <INVENTORY><ubItemIndex>0</ubItemIndex><ubOptimalNumber>4</ubOptimalNumber></INVENTORY>\
+        <INVENTORY><ubItemIndex>5</ubItemIndex><ubOptimalNumber>1</ubOptimalNumber><ubQuality>80</ubQuality></INVENTORY>\
+        </INVENTORYLIST>").unwrap();
+
+    let rules = RuleSet::builtin();
+    let table = rules.table("TonyInventory.xml").unwrap();
+    let inventory = NPCInventory::loadNPCInventory(&source, table).unwrap();
+
+    assert_eq!(inventory.items, [201, 0, 5]);
+    assert_eq!(inventory.warnings(), [
+        "slot 2: ubQuality 80 is dropped, the converted inventory only keeps item and optimal number",
+        "slot 1: ubOptimalNumber 4 of an empty slot is dropped"
+    ]);
+
+    let output = dirpath.join("TonyInventory out.xml");
+    inventory.saveNPCInventory(&output, table).unwrap();
+    let output = std::fs::read_to_string(output).unwrap();
+    assert!(output.contains("<AnItem index = \"201\" optimal = \"2\"/>\n\t\t<AnItem index = \"5\" optimal = \"1\"/>"));
+}
+
+#[test]
+fn convertedInventoriesAreRead()
+{
+    let dirpath = TempDir::new("inventory");
+    let source = dirpath.file("TonyInventory.xml", "<INVENTORYLIST><INVENTORY>\
+        <AnItem index = \"201\" optimal = \"2\"/><AnItem index = \"5\" optimal = \"1\"/>\
+        </INVENTORY></INVENTORYLIST>");
+
+    let rules = RuleSet::builtin();
+    let table = rules.table("TonyInventory.xml").unwrap();
+    let inventory = NPCInventory::loadNPCInventory(&source, table).unwrap();
+    assert_eq!(inventory.items, [201, 5]);
+    assert_eq!(inventory.optimal, [2, 1]);
+
+    let broken = dirpath.file("KeithInventory.xml", "<INVENTORYLIST><INVENTORY><AnItem index = \"x\"/></INVENTORY></INVENTORYLIST>");
+    let result = NPCInventory::loadNPCInventory(&broken, table);
+    assert!(result.is_err_and(|e| e.contains("AnItem index = \"x\"")));
+}
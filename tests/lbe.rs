@@ -0,0 +1,97 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::lbe::{LoadBearingEquipment, Pockets};
+use common::TempDir;
+
+
+#[test]
+fn fieldOrderIsKept()
+{
+    let dirpath = TempDir::new("lbe");
+    let source = dirpath.join(LoadBearingEquipment::FILE);
+    std::fs::write(&source, "<LOADBEARINGEQUIPMENTLIST><LOADBEARINGEQUIPMENT>\
+        <lbeIndex>5</lbeIndex><lbeClass>2</lbeClass><lbeCombo>0</lbeCombo><lbeAvailableVolume>30</lbeAvailableVolume>\
+        <lbePocketIndex1>7</lbePocketIndex1><lbePocketIndex2>0</lbePocketIndex2><lbePocketsAvailable>1</lbePocketsAvailable>\
+        </LOADBEARINGEQUIPMENT></LOADBEARINGEQUIPMENTLIST>").unwrap();
+
+    let rules = RuleSet::builtin();
+    let table = rules.table(LoadBearingEquipment::FILE).unwrap();
+    let lbe = LoadBearingEquipment::loadLoadBearingEquipment(&source, table).unwrap();
+    let output = dirpath.join("LoadBearingEquipment out.xml");
+    lbe.saveLoadBearingEquipment(&output, table).unwrap();
+
+    let output = std::fs::read_to_string(output).unwrap();
+    let tags: Vec<&str> = output.lines().map(|line| line.trim()).collect();
+    assert_eq!(tags, [
+        "<LOADBEARINGEQUIPMENTLIST>",
+        "<LOADBEARINGEQUIPMENT>",
+        "<lbeIndex>5</lbeIndex>",
+        "<lbeClass>2</lbeClass>",
+        "<lbeCombo>0</lbeCombo>",
+        "<lbeAvailableVolume>30</lbeAvailableVolume>",
+        "<LbePocket id = \"1\" pocket = \"7\"/>",
+        "<lbePocketsAvailable>1</lbePocketsAvailable>",
+        "</LOADBEARINGEQUIPMENT>",
+        "</LOADBEARINGEQUIPMENTLIST>"
+    ]);
+}
+
+#[test]
+fn pocketsMustFitTheClass()
+{
+    let mut lbe = LoadBearingEquipment::new();
+    lbe.index = vec![1, 2, 3];
+    lbe.classes = vec![2, 5, 9];
+    lbe.volumes = vec![0, 0, 0];
+    lbe.pockets = vec![vec![0, 10, 11], vec![11], vec![]];
+
+    let mut pockets = Pockets::new();
+    pockets.index = vec![10, 11];
+    pockets.names = vec!["Small Vest Pocket".to_string(), "Gun Sling".to_string()];
+    pockets.types = vec![1, 2];
+    pockets.volumes = vec![5, 10];
+
+    let warnings = lbe.checkPockets(&pockets);
+    assert_eq!(warnings.len(), 2, "{:?}", warnings);
+    assert!(warnings[0].starts_with("LBE 1 (vest): lbePocketIndex2 references pocket 11 (Gun Sling) of pType 2"));
+    assert!(warnings[1].contains("lbeClass 9 is not a known LBE class"));
+}
+
+#[test]
+fn convertedFilesKeepTheirPockets()
+{
+    let dirpath = TempDir::new("lbe");
+    let source = dirpath.file(LoadBearingEquipment::FILE, "<LOADBEARINGEQUIPMENTLIST><LOADBEARINGEQUIPMENT>\
+        <lbeIndex>5</lbeIndex><lbeClass>2</lbeClass><lbeFiller/>\
+        <LbePocket id = \"1\" pocket = \"7\"/><LbePocket id = \"3\" pocket = \"9\"/>\
+        </LOADBEARINGEQUIPMENT></LOADBEARINGEQUIPMENTLIST>");
+
+    let rules = RuleSet::builtin();
+    let table = rules.table(LoadBearingEquipment::FILE).unwrap();
+    let lbe = LoadBearingEquipment::loadLoadBearingEquipment(&source, table).unwrap();
+    assert_eq!(lbe.pockets[0], [0, 7, 0, 9]);
+    assert_eq!(lbe.fields[0].last().unwrap(), &("lbeFiller".to_string(), String::new()));
+
+    let output = dirpath.join("LoadBearingEquipment out.xml");
+    lbe.saveLoadBearingEquipment(&output, table).unwrap();
+    let output = std::fs::read_to_string(output).unwrap();
+    assert!(output.contains("<lbeFiller></lbeFiller>"), "{}", output);
+    assert!(output.contains("<LbePocket id = \"3\" pocket = \"9\"/>"), "{}", output);
+}
+
+#[test]
+fn pocketSlotsOutsideTheItemAreRejected()
+{
+    let dirpath = TempDir::new("lbe");
+    let source = dirpath.file(LoadBearingEquipment::FILE, "<LOADBEARINGEQUIPMENTLIST><LOADBEARINGEQUIPMENT>\
+        <lbeIndex>5</lbeIndex><lbePocketIndex4000000>7</lbePocketIndex4000000>\
+        </LOADBEARINGEQUIPMENT></LOADBEARINGEQUIPMENTLIST>");
+
+    let rules = RuleSet::builtin();
+    let table = rules.table(LoadBearingEquipment::FILE).unwrap();
+    let result = LoadBearingEquipment::loadLoadBearingEquipment(&source, table);
+    assert!(result.is_err_and(|e| e.contains("4000000")));
+}
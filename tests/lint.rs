@@ -0,0 +1,25 @@
+#![allow(non_snake_case)]
+
+use JA2_ConvertXMLData::lint::{lintMercOpinions, LintConfig, LINT_RULES};
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+
+
+#[test]
+fn findingsNameTheirRule()
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = vec![1, 2];
+    mercOpinions.nicknames = vec!["Ira".to_string(), "ira".to_string()];
+    mercOpinions.opinions = vec![vec![0; 10], vec![0; 10]];
+    mercOpinions.opinions[0][1] = 5;
+    mercOpinions.opinions[0][9] = 2;
+    mercOpinions.opinions[1][2] = 200;
+
+    let findings = lintMercOpinions(&mercOpinions, None, &LintConfig::new());
+    let rules: Vec<&str> = findings.iter().map(|f| f.rule).collect();
+    assert_eq!(rules, ["MO002", "MO001", "MO002", "MO003", "MO005"]);
+    for finding in &findings
+    {
+        assert!(LINT_RULES.iter().any(|(id, name, _, _)| *id == finding.rule && *name == finding.name));
+    }
+}
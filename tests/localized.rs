@@ -0,0 +1,51 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::localized;
+use common::TempDir;
+
+
+#[test]
+fn missingAndExtraRecordsAreReported()
+{
+    let dirpath = TempDir::new("localized");
+    let basepath = dirpath.join("Items.xml");
+    let filepath = dirpath.join("German.Items.xml");
+    std::fs::write(&basepath, "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEM><ITEM><uiIndex>2</uiIndex></ITEM></ITEMLIST>").unwrap();
+    std::fs::write(&filepath, "<ITEMLIST><ITEM><uiIndex>2</uiIndex></ITEM><ITEM><uiIndex>3</uiIndex></ITEM></ITEMLIST>").unwrap();
+
+    let rules = RuleSet::builtin();
+    let warnings = localized::checkTranslation(&filepath, &basepath, rules.table("Items.xml").unwrap()).unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0], "uiIndex 1 is missing from the translation");
+    assert!(warnings[1].starts_with("uiIndex 3 is not in "));
+}
+
+#[test]
+fn duplicateTranslationsAreReported()
+{
+    let dirpath = TempDir::new("localized");
+    let basepath = dirpath.file("Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEM></ITEMLIST>");
+    let filepath = dirpath.file("German.Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEM><ITEM><uiIndex>1</uiIndex></ITEM></ITEMLIST>");
+
+    let rules = RuleSet::builtin();
+    let warnings = localized::checkTranslation(&filepath, &basepath, rules.table("Items.xml").unwrap()).unwrap();
+    assert_eq!(warnings, ["uiIndex 1 is translated more than once"]);
+}
+
+#[test]
+fn brokenTranslationsAreAnError()
+{
+    let dirpath = TempDir::new("localized");
+    let basepath = dirpath.file("Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEM></ITEMLIST>");
+    let filepath = dirpath.file("German.Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEMS></ITEMLIST>");
+    let rules = RuleSet::builtin();
+    let table = rules.table("Items.xml").unwrap();
+
+    let result = localized::checkTranslation(&filepath, &basepath, table);
+    assert!(result.is_err_and(|e| e.contains("German.Items.xml")));
+    let result = localized::checkTranslation(&dirpath.join("German.Missing.xml"), &basepath, table);
+    assert!(result.is_err_and(|e| e.contains("Could not open file")));
+}
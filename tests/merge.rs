@@ -0,0 +1,80 @@
+#![allow(non_snake_case)]
+
+use JA2_ConvertXMLData::merge::Merge;
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+
+
+fn mercOpinions(mercs: &[(u8, &str)]) -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = mercs.iter().map(|(i, _)| *i).collect();
+    mercOpinions.nicknames = mercs.iter().map(|(_, n)| n.to_string()).collect();
+    mercOpinions.opinions = vec![vec![0; 5]; mercs.len()];
+    mercOpinions
+}
+
+fn conflicts(merge: &Merge) -> Vec<(u8, &str)>
+{
+    merge.conflicts.iter().map(|c| (c.merc, c.message.as_str())).collect()
+}
+
+
+#[test]
+fn removedRecordsChangedOnTheOtherSideAreConflicts()
+{
+    // Ours removes Fox, which theirs changes, and changes Ivan, which theirs removes. Gus is
+    // removed by theirs and unchanged in ours.
+    let base = mercOpinions(&[(1, "Ira"), (2, "Fox"), (3, "Ivan"), (4, "Gus")]);
+    let mut ours = mercOpinions(&[(1, "Ira"), (3, "Ivan"), (4, "Gus")]);
+    ours.opinions[1][1] = -3;
+    let mut theirs = mercOpinions(&[(1, "Ira"), (2, "Fox")]);
+    theirs.opinions[1][3] = 4;
+
+    let expected = [(3, "Ivan (3): removed in theirs, changed in ours"), (2, "Fox (2): removed in ours, changed in theirs")];
+
+    let merge = Merge::mergeMercOpinions(&base, &ours, &theirs, false);
+    assert_eq!(conflicts(&merge), expected);
+    assert_eq!(merge.merged.index, [1, 3]);
+    assert_eq!(merge.merged.opinions[1], [0, -3, 0, 0, 0]);
+
+    let merge = Merge::mergeMercOpinions(&base, &ours, &theirs, true);
+    assert_eq!(conflicts(&merge), expected);
+    assert_eq!(merge.merged.index, [1, 2]);
+    assert_eq!(merge.merged.opinions[1], [0, 0, 0, 4, 0]);
+}
+
+#[test]
+fn nicknamesChangedOnBothSidesAreConflicts()
+{
+    let base = mercOpinions(&[(1, "Ira"), (2, "Fox")]);
+    let ours = mercOpinions(&[(1, "Ira R"), (2, "Fox")]);
+    let theirs = mercOpinions(&[(1, "Ira Rebecca"), (2, "Cynthia")]);
+
+    for (preferTheirs, nickname) in [(false, "Ira R"), (true, "Ira Rebecca")]
+    {
+        let merge = Merge::mergeMercOpinions(&base, &ours, &theirs, preferTheirs);
+        assert_eq!(conflicts(&merge), [(1, "Nickname of 1: base Ira, ours Ira R, theirs Ira Rebecca")]);
+        // Fox was only renamed by theirs
+        assert_eq!(merge.merged.nicknames, [nickname, "Cynthia"]);
+    }
+}
+
+#[test]
+fn opinionsChangedOnBothSidesAreConflicts()
+{
+    let mercs = [(1, "Ira"), (2, "Fox")];
+    let mut base = mercOpinions(&mercs);
+    base.opinions[0][2] = 1;
+    let mut ours = mercOpinions(&mercs);
+    ours.opinions[0][2] = 2;
+    ours.opinions[1][1] = 5;
+    let mut theirs = mercOpinions(&mercs);
+    theirs.opinions[0][2] = -2;
+
+    for (preferTheirs, value) in [(false, 2), (true, -2)]
+    {
+        let merge = Merge::mergeMercOpinions(&base, &ours, &theirs, preferTheirs);
+        assert_eq!(conflicts(&merge), [(1, "Ira -> Fox: base +1, ours +2, theirs -2")]);
+        assert_eq!(merge.merged.opinions, [vec![0, 0, value], vec![0, 5]]);
+    }
+}
@@ -0,0 +1,79 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+use JA2_ConvertXMLData::patch::OpinionPatch;
+use common::TempDir;
+
+
+// uiIndex, nickname and the non-zero opinions
+type Record<'a> = (u8, &'a str, &'a [(usize, i32)]);
+
+fn mercOpinions(records: &[Record]) -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    for (uiIndex, nickname, opinions) in records
+    {
+        let mut row = vec![0; 10];
+        for (id, modifier) in opinions.iter() { row[*id] = *modifier; }
+        mercOpinions.index.push(*uiIndex);
+        mercOpinions.nicknames.push(nickname.to_string());
+        mercOpinions.opinions.push(row);
+    }
+    mercOpinions
+}
+
+fn trimmed(row: &[i32]) -> Vec<i32>
+{
+    let end = row.iter().rposition(|m| *m != 0).map_or(0, |k| k + 1);
+    row[..end].to_vec()
+}
+
+
+#[test]
+fn savedPatchesRecreateTheModdedFile()
+{
+    let dirpath = TempDir::new("patch");
+    let base = mercOpinions(&[(1, "Ira", &[(2, 5)]), (2, "Fox", &[(1, -3)]), (4, "Ivan", &[])]);
+    let modded = mercOpinions(&[(1, "Ira", &[(2, 5), (4, 1)]), (2, "Foxy", &[]), (3, "Grizzly", &[(1, 7)])]);
+    let patch = OpinionPatch::makePatch(&base, &modded);
+
+    for name in ["patch.xml", "patch.json", "patch.yaml"]
+    {
+        let filepath = dirpath.join(name);
+        patch.save(&filepath).unwrap();
+        let result = OpinionPatch::load(&filepath).unwrap().applyPatch(&base).unwrap();
+
+        assert_eq!(result.index, modded.index, "{}", name);
+        assert_eq!(result.nicknames, modded.nicknames, "{}", name);
+        for k in 0..modded.index.len()
+        {
+            assert_eq!(trimmed(&result.opinions[k]), trimmed(&modded.opinions[k]), "{} record {}", name, k);
+        }
+    }
+}
+
+#[test]
+fn opinionIdsAboveTheGameLimitAreRejected()
+{
+    let dirpath = TempDir::new("patch");
+    let filepath = dirpath.join("limit.json");
+    std::fs::write(&filepath, r#"{"table": "MercOpinions.xml", "records": [{"uiIndex": 1, "action": "change", "opinions": [{"id": 4000000000, "modifier": 5}]}]}"#).unwrap();
+
+    let base = mercOpinions(&[(1, "Ira", &[])]);
+    let result = OpinionPatch::load(&filepath).unwrap().applyPatch(&base);
+    assert!(result.is_err_and(|e| e.contains("4000000000")));
+}
+
+#[test]
+fn xmlPatchesWithOpinionIdsAboveTheGameLimitAreRejected()
+{
+    let dirpath = TempDir::new("patch");
+    let filepath = dirpath.join("limit.xml");
+    std::fs::write(&filepath, "<PATCH table = \"MercOpinions.xml\"><OPINION uiIndex = \"1\" action = \"change\">\
+        <AnOpinion id = \"4000000000\" modifier = \"5\"/></OPINION></PATCH>").unwrap();
+
+    let result = OpinionPatch::load(&filepath);
+    assert!(result.is_err_and(|e| e.contains("4000000000")));
+}
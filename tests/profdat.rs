@@ -0,0 +1,77 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+use JA2_ConvertXMLData::profdat::{self, ProfDat, RECORD_SIZE, NICKNAME_OFFSET, OPINIONS_OFFSET};
+use common::TempDir;
+
+
+// Two profiles as the game stores them, with some noise in the fields this tool doesn't read
+fn profDatFile() -> Vec<u8>
+{
+    let mut data = Vec::new();
+    for (profile, nickname) in ["Barry", "Blood"].iter().enumerate()
+    {
+        let mut record: Vec<u8> = (0..RECORD_SIZE).map(|i| (i * 7 + profile) as u8).collect();
+        record[NICKNAME_OFFSET..NICKNAME_OFFSET + 20].fill(0);
+        for (i, c) in nickname.encode_utf16().enumerate()
+        {
+            record[NICKNAME_OFFSET + i * 2..NICKNAME_OFFSET + i * 2 + 2].copy_from_slice(&c.to_le_bytes());
+        }
+        record[OPINIONS_OFFSET..OPINIONS_OFFSET + 75].fill(0);
+        record[OPINIONS_OFFSET + 1 - profile] = (-5i8 + profile as i8) as u8;
+        record[OPINIONS_OFFSET + 74] = 9;
+        profdat::encrypt(&mut record);
+        data.extend(record);
+    }
+    data
+}
+
+#[test]
+fn encryptionRoundTrips()
+{
+    let plain: Vec<u8> = (0..RECORD_SIZE).map(|i| (i * 13) as u8).collect();
+    let mut record = plain.clone();
+    profdat::encrypt(&mut record);
+    assert_ne!(record, plain);
+    profdat::decrypt(&mut record);
+    assert_eq!(record, plain);
+}
+
+#[test]
+fn recordsAreDecrypted()
+{
+    let dirpath = TempDir::new("profdat");
+    let source = dirpath.join(ProfDat::FILE);
+    std::fs::write(&source, profDatFile()).unwrap();
+
+    let profDat = ProfDat::loadProfDat(&source).unwrap();
+    assert_eq!(profDat.nickname(0), "Barry");
+    assert_eq!(profDat.nickname(1), "Blood");
+    assert_eq!(profDat.opinions(0)[1], -5);
+    assert_eq!(profDat.opinions(1)[0], -4);
+    assert_eq!(profDat.opinions(1).len(), 75);
+}
+
+#[test]
+fn importAndExportGiveBackTheSameFile()
+{
+    let dirpath = TempDir::new("profdat");
+    let source = dirpath.join("Original Prof.dat");
+    std::fs::write(&source, profDatFile()).unwrap();
+
+    let rules = RuleSet::builtin();
+    let table = rules.table(MercOpinions::FILE).unwrap();
+    let xmlpath = dirpath.join("MercOpinions out.xml");
+    ProfDat::loadProfDat(&source).unwrap().toMercOpinions().saveMercOpinions(&xmlpath, table).unwrap();
+
+    let mercOpinions = MercOpinions::loadMercOpinions(&xmlpath, table).unwrap();
+    let mut profDat = ProfDat::loadProfDat(&source).unwrap();
+    profDat.applyMercOpinions(&mercOpinions);
+    let output = dirpath.join("Prof out.dat");
+    profDat.saveProfDat(&output);
+
+    assert!(std::fs::read(output).unwrap() == profDatFile());
+}
@@ -0,0 +1,76 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+use JA2_ConvertXMLData::profiles::MercProfiles;
+use common::TempDir;
+
+
+#[test]
+fn profilesWithoutRecordKeepTheirOpinions()
+{
+    let dirpath = TempDir::new("profiles");
+    let source = dirpath.join(MercProfiles::FILE);
+    std::fs::write(&source, "<PROFILES>
+	<PROFILE>
+		<uiIndex>1</uiIndex>
+		<zNickname>Ivan</zNickname>
+		<Opinions>
+			<AnOpinion id = \"2\" modifier = \"5\"/>
+		</Opinions>
+	</PROFILE>
+	<PROFILE>
+		<uiIndex>2</uiIndex>
+		<zNickname>Igor</zNickname>
+		<Opinions>
+			<AnOpinion id = \"1\" modifier = \"-3\"/>
+		</Opinions>
+	</PROFILE>
+</PROFILES>").unwrap();
+
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = vec![1];
+    mercOpinions.nicknames = vec!["Ivan".to_string()];
+    mercOpinions.opinions = vec![vec![0, 0, 7]];
+
+    let rules = RuleSet::builtin();
+    let rule = rules.table(MercOpinions::FILE).unwrap().rules.first().unwrap();
+    let output = dirpath.join("MercProfiles out.xml");
+    MercProfiles::injectOpinions(&source, &output, &mercOpinions, rule).unwrap();
+
+    let profiles = MercProfiles::loadMercProfiles(&output).unwrap();
+    assert_eq!(profiles.opinions, [vec![0, 0, 7], vec![0, -3]]);
+}
+
+#[test]
+fn opinionIdsAboveTheGameLimitAreAnError()
+{
+    let dirpath = TempDir::new("profiles");
+    let source = dirpath.join("Huge MercProfiles.xml");
+    std::fs::write(&source, "<PROFILES><PROFILE><uiIndex>1</uiIndex><Opinions><AnOpinion id = \"4000000000\" modifier = \"1\"/></Opinions></PROFILE></PROFILES>").unwrap();
+
+    let result = MercProfiles::loadMercProfiles(&source);
+    assert!(result.is_err_and(|e| e.contains("4000000000")));
+}
+
+#[test]
+fn missingAndBrokenProfilesAreAnError()
+{
+    let dirpath = TempDir::new("profiles");
+    let missing = dirpath.join("Typo.xml");
+    let result = MercProfiles::loadMercProfiles(&missing);
+    assert!(result.is_err_and(|e| e.contains("Could not open file")));
+
+    let rules = RuleSet::builtin();
+    let rule = rules.table(MercOpinions::FILE).unwrap().rules.first().unwrap();
+    let output = dirpath.join("MercProfiles out.xml");
+    let result = MercProfiles::injectOpinions(&missing, &output, &MercOpinions::new(), rule);
+    assert!(result.is_err_and(|e| e.contains("Could not open file")));
+
+    let broken = dirpath.file(MercProfiles::FILE, "<PROFILES><PROFILE><uiIndex>1</uiIndex></PROFILES>");
+    let result = MercProfiles::injectOpinions(&broken, &output, &MercOpinions::new(), rule);
+    assert!(result.is_err_and(|e| e.contains(MercProfiles::FILE)));
+    assert!(!output.exists());
+}
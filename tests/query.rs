@@ -0,0 +1,56 @@
+#![allow(non_snake_case)]
+
+use JA2_ConvertXMLData::query::{queryMerc, queryPair};
+use JA2_ConvertXMLData::mercopinions::MercOpinions;
+
+
+// Ira likes Fox and dislikes Ivan more, Fox and Ivan both like Ira. Merc 4 has no record.
+fn mercOpinions() -> MercOpinions
+{
+    let mut mercOpinions = MercOpinions::new();
+    mercOpinions.index = vec![1, 2, 3];
+    mercOpinions.nicknames = vec!["Ira".to_string(), "Fox".to_string(), "Ivan".to_string()];
+    mercOpinions.opinions = vec![vec![0; 5]; 3];
+    mercOpinions.opinions[0][1] = 9;
+    mercOpinions.opinions[0][2] = 3;
+    mercOpinions.opinions[0][3] = -7;
+    mercOpinions.opinions[0][4] = 1;
+    mercOpinions.opinions[1][1] = 2;
+    mercOpinions.opinions[2][1] = 5;
+    mercOpinions
+}
+
+
+#[test]
+fn opinionsOfAndAboutAMercAreListedStrongestFirst()
+{
+    assert_eq!(queryMerc(&mercOpinions(), 1), [
+        "Ira (1)",
+        "Opinions of Ira:",
+        "  -> Ivan (3): -7",
+        "  -> Fox (2): +3",
+        "  -> 4 (4): +1",
+        "Opinions about Ira:",
+        "  Ivan (3): +5",
+        "  Fox (2): +2"
+    ]);
+}
+
+#[test]
+fn mercsWithoutARecordAreListed()
+{
+    assert_eq!(queryMerc(&mercOpinions(), 4), [
+        "4 (4)",
+        "  has no record in the file",
+        "Opinions of 4:",
+        "Opinions about 4:",
+        "  Ira (1): +1"
+    ]);
+}
+
+#[test]
+fn pairsShowBothDirections()
+{
+    assert_eq!(queryPair(&mercOpinions(), 1, 3), ["Ira -> Ivan: -7", "Ivan -> Ira: +5"]);
+    assert_eq!(queryPair(&mercOpinions(), 2, 4), ["Fox -> 4: 0", "4 -> Fox: 0"]);
+}
@@ -0,0 +1,38 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::references::ReferenceSet;
+use common::TempDir;
+
+
+#[test]
+fn danglingReferencesAreFound()
+{
+    let dirpath = TempDir::new("references");
+    dirpath.file("Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEM><ITEM><uiIndex>2</uiIndex></ITEM></ITEMLIST>");
+    dirpath.file("Attachments.xml", "<ATTACHMENTLIST>\
+        <ATTACHMENT><attachmentIndex>1</attachmentIndex><itemIndex>2</itemIndex></ATTACHMENT>\
+        <ATTACHMENT><attachmentIndex>9</attachmentIndex><itemIndex>0</itemIndex></ATTACHMENT>\
+        </ATTACHMENTLIST>");
+    dirpath.file("TonyInventory.xml", "<INVENTORYLIST><INVENTORY><AnItem index = \"2\" optimal = \"1\"/><AnItem index = \"5\" optimal = \"1\"/></INVENTORY></INVENTORYLIST>");
+    dirpath.file("Magazines.xml", "<MAGAZINELIST><MAGAZINE><ubCalibre>1</ubCalibre></MAGAZINE></MAGAZINELIST>");
+
+    let (broken, notes) = ReferenceSet::builtin().unwrap().checkDirectory(&dirpath).unwrap();
+    assert_eq!(broken, [
+        "Attachments.xml: ATTACHMENT 2: attachmentIndex = 9 refers to uiIndex 9, which does not exist in Items.xml",
+        "TonyInventory.xml: INVENTORY 1: AnItem.index = 5 refers to uiIndex 5, which does not exist in Items.xml"
+    ]);
+    assert!(notes.iter().any(|n| n.starts_with("AmmoStrings.xml not found")), "{:?}", notes);
+}
+
+#[test]
+fn brokenFilesAreAnError()
+{
+    let dirpath = TempDir::new("references");
+    dirpath.file("Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex></ITEMS></ITEMLIST>");
+    dirpath.file("Attachments.xml", "<ATTACHMENTLIST><ATTACHMENT><itemIndex>1</itemIndex></ATTACHMENT></ATTACHMENTLIST>");
+
+    let result = ReferenceSet::builtin().unwrap().checkDirectory(&dirpath);
+    assert!(result.is_err_and(|e| e.contains("Items.xml")));
+}
@@ -0,0 +1,63 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use std::path::Path;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::schema::TableSchema;
+use common::TempDir;
+
+
+#[test]
+fn convertedFilesFindTheirTable()
+{
+    let rules = RuleSet::builtin();
+    for name in ["MercOpinions.xml", "MercOpinions out.xml", "German.MercOpinions out.xml"]
+    {
+        assert!(rules.forOutput(Path::new(name)).is_some_and(|t| t.file == "MercOpinions.xml"), "{}", name);
+    }
+    assert!(rules.forFile(Path::new("MercOpinions out.xml")).is_none());
+    assert!(rules.forOutput(Path::new("Unknown out.xml")).is_none());
+}
+
+#[test]
+fn opinionsOutsideTheGameRangeAreInvalid()
+{
+    let dirpath = TempDir::new("schema");
+    let filepath = dirpath.join("MercOpinions out.xml");
+    std::fs::write(&filepath, "<MERCOPINIONS>\n\t<OPINION>\n\t\t<uiIndex>1</uiIndex>\n\
+        \t\t<AnOpinion id = \"254\" modifier = \"-128\"/>\n\
+        \t\t<AnOpinion id = \"255\" modifier = \"127\"/>\n\
+        \t\t<AnOpinion id = \"3\" modifier = \"128\"/>\n\
+        \t</OPINION>\n</MERCOPINIONS>\n").unwrap();
+
+    let rules = RuleSet::builtin();
+    let schema = TableSchema::fromTable(rules.forOutput(&filepath).unwrap());
+    let errors = schema.validate(&filepath).unwrap();
+    assert_eq!(errors, [
+        "MercOpinions out.xml:5: AnOpinion id: \"255\" is not a number from 0 to 254",
+        "MercOpinions out.xml:6: AnOpinion modifier: \"128\" is not a number from -128 to 127"
+    ]);
+}
+
+#[test]
+fn schemasBoundOpinions()
+{
+    let rules = RuleSet::builtin();
+    let schema = TableSchema::fromTable(rules.table("MercOpinions.xml").unwrap());
+
+    let xsd = schema.toXsd();
+    assert!(xsd.contains("<xs:attribute name=\"id\" type=\"opinionId\"/>"));
+    assert!(xsd.contains("<xs:attribute name=\"modifier\" type=\"xs:byte\" use=\"required\"/>"));
+    assert!(xsd.contains("<xs:restriction base=\"xs:unsignedByte\">\n\t\t\t<xs:maxInclusive value=\"254\"/>"));
+
+    let json = schema.toJsonSchema();
+    let opinion = &json["properties"]["records"]["items"]["properties"]["opinions"]["items"]["properties"];
+    assert_eq!(opinion["id"]["maximum"], 254);
+    assert_eq!(opinion["modifier"]["minimum"], -128);
+    assert_eq!(opinion["modifier"]["maximum"], 127);
+
+    let items = TableSchema::fromTable(rules.table("Items.xml").unwrap()).toXsd();
+    assert!(!items.contains("opinionId"));
+}
@@ -0,0 +1,92 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::strings::{Translations, StringEntry, readStrings};
+use common::TempDir;
+
+
+fn entry(key: &str, translation: &str) -> StringEntry
+{
+    StringEntry{key: key.to_string(), source: String::new(), translation: translation.to_string()}
+}
+
+#[test]
+fn stringsBeforeTheIndexAndEmptyFieldsAreTranslated()
+{
+    let dirpath = TempDir::new("strings");
+    let source = dirpath.join("German.MercOpinions.xml");
+    std::fs::write(&source, "<MERCOPINIONS>\
+        <OPINION><zNickname>Barry</zNickname><uiIndex>1</uiIndex></OPINION>\
+        <OPINION><uiIndex>2</uiIndex><zNickname/></OPINION>\
+        <OPINION><uiIndex>3</uiIndex><zNickname>Lynx</zNickname></OPINION>\
+        </MERCOPINIONS>").unwrap();
+
+    let rules = RuleSet::builtin();
+    let table = rules.table("MercOpinions.xml").unwrap();
+    assert_eq!(readStrings(&source, table).unwrap(), [
+        ("1".to_string(), "zNickname".to_string(), "Barry".to_string()),
+        ("2".to_string(), "zNickname".to_string(), String::new()),
+        ("3".to_string(), "zNickname".to_string(), "Lynx".to_string())
+    ]);
+
+    let mut translations = Translations::new();
+    translations.entries.push(entry("MercOpinions.xml/1/zNickname", "Bärchen"));
+    translations.entries.push(entry("MercOpinions.xml/2/zNickname", "Blut"));
+    let output = dirpath.join("German.MercOpinions out.xml");
+    translations.injectStrings(&source, &output, table).unwrap();
+
+    assert_eq!(std::fs::read_to_string(output).unwrap(), "<MERCOPINIONS>\
+        <OPINION><zNickname>Bärchen</zNickname><uiIndex>1</uiIndex></OPINION>\
+        <OPINION><uiIndex>2</uiIndex><zNickname>Blut</zNickname></OPINION>\
+        <OPINION><uiIndex>3</uiIndex><zNickname>Lynx</zNickname></OPINION>\
+        </MERCOPINIONS>");
+}
+
+#[test]
+fn translationsAreMatchedToTheBaseFile()
+{
+    let dirpath = TempDir::new("strings");
+    let basepath = dirpath.join("MercOpinions.xml");
+    let filepath = dirpath.join("Russian.MercOpinions.xml");
+    std::fs::write(&basepath, "<MERCOPINIONS><OPINION><uiIndex>1</uiIndex><zNickname>Ivan</zNickname></OPINION>\
+        <OPINION><uiIndex>2</uiIndex><zNickname>Igor</zNickname></OPINION></MERCOPINIONS>").unwrap();
+    std::fs::write(&filepath, "<MERCOPINIONS><OPINION><zNickname>Иван</zNickname><uiIndex>1</uiIndex></OPINION></MERCOPINIONS>").unwrap();
+
+    let rules = RuleSet::builtin();
+    let translations = Translations::extractStrings(&filepath, Some(&basepath), rules.table("MercOpinions.xml").unwrap()).unwrap();
+    let entries: Vec<(&str, &str, &str)> = translations.entries.iter()
+        .map(|e| (e.key.as_str(), e.source.as_str(), e.translation.as_str()))
+        .collect();
+    assert_eq!(entries, [("MercOpinions.xml/1/zNickname", "Ivan", "Иван"), ("MercOpinions.xml/2/zNickname", "Igor", "")]);
+}
+
+#[test]
+fn brokenTablesAreAnError()
+{
+    let dirpath = TempDir::new("strings");
+    let source = dirpath.file("Items.xml", "<ITEMLIST><ITEM><uiIndex>1</uiIndex><szItemName>a &bogus; b</szItemName></ITEM></ITEMLIST>");
+    let rules = RuleSet::builtin();
+    let table = rules.table("Items.xml").unwrap();
+
+    assert!(readStrings(&source, table).is_err_and(|e| e.contains("Items.xml")));
+    assert!(readStrings(&dirpath.join("Missing.xml"), table).is_err_and(|e| e.contains("Could not open file")));
+
+    let output = dirpath.join("Items out.xml");
+    let result = Translations::new().injectStrings(&source.with_file_name("Missing.xml"), &output, table);
+    assert!(result.is_err_and(|e| e.contains("Could not open file")));
+    assert!(!output.exists());
+}
+
+#[test]
+fn theFormatIsGivenBySaveNotTheExtension()
+{
+    let dirpath = TempDir::new("strings");
+    let mut translations = Translations::new();
+    translations.entries.push(entry("Items.xml/1/szItemName", "Messer"));
+
+    let output = dirpath.join("Items.po");
+    translations.save(&output, true).unwrap();
+    assert!(std::fs::read_to_string(&output).unwrap().contains("<trans-unit id=\"Items.xml/1/szItemName\">"));
+}
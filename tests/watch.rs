@@ -0,0 +1,53 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use std::fs::File;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use JA2_ConvertXMLData::watch::{Watcher, DEBOUNCE};
+use common::TempDir;
+
+
+// Modification times are set explicitly, file systems with a coarse clock would miss quick writes
+fn touch(filepath: &Path, seconds: u64)
+{
+    let file = File::options().create(true).truncate(false).write(true).open(filepath).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 + seconds)).unwrap();
+}
+
+
+#[test]
+fn changesAreReportedOnceSettled()
+{
+    let dirpath = TempDir::new("watch");
+    let xmlpath = dirpath.join("MercOpinions.xml");
+    touch(&xmlpath, 0);
+    touch(&dirpath.join("notes.txt"), 0);
+
+    let watched = |path: &Path| path.extension().is_some_and(|e| e == "xml");
+    let mut watcher = Watcher::new(&dirpath, &watched).unwrap();
+    assert_eq!(watcher.files(), 1);
+    assert!(watcher.poll(&watched).unwrap().is_empty());
+
+    // A save in several writes is reported once, after the last one
+    touch(&xmlpath, 1);
+    assert!(watcher.poll(&watched).unwrap().is_empty());
+    std::thread::sleep(DEBOUNCE / 5);
+    touch(&xmlpath, 2);
+    assert!(watcher.poll(&watched).unwrap().is_empty());
+    std::thread::sleep(DEBOUNCE);
+    assert_eq!(watcher.poll(&watched).unwrap(), std::slice::from_ref(&xmlpath));
+    assert!(watcher.poll(&watched).unwrap().is_empty());
+
+    // New files are reported, files removed before they settle and unwatched ones are not
+    let newpath = dirpath.join("Items.xml");
+    touch(&newpath, 0);
+    touch(&xmlpath, 3);
+    touch(&dirpath.join("notes.txt"), 1);
+    assert!(watcher.poll(&watched).unwrap().is_empty());
+    std::fs::remove_file(&xmlpath).unwrap();
+    std::thread::sleep(DEBOUNCE);
+    assert_eq!(watcher.poll(&watched).unwrap(), [newpath]);
+}
@@ -0,0 +1,52 @@
+#![allow(non_snake_case)]
+
+mod common;
+
+use JA2_ConvertXMLData::rules::RuleSet;
+use JA2_ConvertXMLData::weapons::Weapons;
+use common::TempDir;
+
+
+#[test]
+fn invalidValuesAreReadAsZero()
+{
+    let dirpath = TempDir::new("weapons");
+    let source = dirpath.file(Weapons::FILE, "<WEAPONLIST><WEAPON>\
+        <uiIndex>7</uiIndex><ubShotsPerBurst>3</ubShotsPerBurst><sSound>-1</sSound><sBurstSound></sBurstSound>\
+        <ubBurstAPs>5</ubBurstAPs><fSilenced>0</fSilenced>\
+        </WEAPON></WEAPONLIST>");
+
+    let rules = RuleSet::builtin();
+    let table = rules.table(Weapons::FILE).unwrap();
+    let weapons = Weapons::loadWeapons(&source, table).unwrap();
+
+    let weapon = &weapons.weapons[0];
+    assert_eq!(weapon.fields, [
+        ("uiIndex".to_string(), "7".to_string()),
+        ("ubShotsPerBurst".to_string(), "3".to_string()),
+        ("sSound".to_string(), "0".to_string()),
+        ("sBurstSound".to_string(), "0".to_string()),
+        ("bBurstAP".to_string(), "5".to_string())
+    ]);
+    assert!(!weapon.derivedFields().iter().any(|(name, _)| name == "sBurstSound"));
+
+    let warnings = weapons.warnings();
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert!(warnings[0].starts_with("fSilenced has no modern equivalent"));
+}
+
+#[test]
+fn fieldsUnderBothNamesKeepTheFirstValue()
+{
+    let dirpath = TempDir::new("weapons");
+    let source = dirpath.file(Weapons::FILE, "<WEAPONLIST><WEAPON>\
+        <ubBurstAPs>5</ubBurstAPs><bBurstAP>4</bBurstAP><uiIndex>7</uiIndex>\
+        </WEAPON></WEAPONLIST>");
+
+    let rules = RuleSet::builtin();
+    let table = rules.table(Weapons::FILE).unwrap();
+    let weapons = Weapons::loadWeapons(&source, table).unwrap();
+
+    assert_eq!(weapons.weapons[0].fields[0], ("bBurstAP".to_string(), "5".to_string()));
+    assert_eq!(weapons.warnings(), ["weapon 7: ubBurstAPs '5' was kept, bBurstAP '4' was dropped as it is the same field"]);
+}